@@ -1,4 +1,4 @@
-use std::{borrow::Cow, sync::LazyLock};
+use std::borrow::Cow;
 
 use radix_trie::{Trie, TrieCommon};
 use rustyline::{
@@ -11,29 +11,20 @@ use rustyline::{
     validate::{ValidationContext, ValidationResult, Validator},
 };
 
-use crate::{
-    GLOBAL_VEC, builtin_commands::BuiltinCommand, utils::find_all_executable_file_in_paths,
-};
+use crate::{builtin_commands::BuiltinCommand, path_cache::command_cache};
 pub struct MyCompleter;
 use strum::IntoEnumIterator;
 
-static GLOBAL_TRIES: LazyLock<Trie<String, ()>> = LazyLock::new(|| {
+/// 内置命令与 PATH 中可执行文件组成的前缀树，每次补全时重建以反映 PATH 变化
+fn command_trie() -> Trie<String, ()> {
     let iter = BuiltinCommand::iter();
     let mut commands: Vec<String> = iter.map(|cmd| cmd.to_string()).collect();
-    commands.extend(
-        find_all_executable_file_in_paths(&GLOBAL_VEC)
-            .iter()
-            .filter_map(|path| {
-                path.file_name()                // Option<&OsStr>
-                .and_then(|name| name.to_str()) // Option<&str>
-                .map(|s| s.to_string())
-            }),
-    );
+    commands.extend(command_cache().all_commands());
     commands
         .into_iter()
-        .map(|cmd| (cmd.to_string(), ()))
+        .map(|cmd| (cmd, ()))
         .collect::<Trie<String, ()>>()
-});
+}
 
 impl Completer for MyCompleter {
     type Candidate = Pair;
@@ -46,7 +37,7 @@ impl Completer for MyCompleter {
     ) -> Result<(usize, Vec<Pair>), ReadlineError> {
         let _start = 0; // 从行首开始补全
         let prefix = &line[..pos];
-        let prefix_keys: Vec<Pair> = GLOBAL_TRIES
+        let prefix_keys: Vec<Pair> = command_trie()
             .get_raw_descendant(prefix)
             .map(|trie| {
                 trie.keys()
@@ -61,7 +52,8 @@ impl Completer for MyCompleter {
     }
     fn update(&self, line: &mut LineBuffer, start: usize, elected: &str, cl: &mut Changeset) {
         let end = line.pos();
-        let elected = if let Some(sub_trie) = GLOBAL_TRIES.subtrie(elected)
+        let trie = command_trie();
+        let elected = if let Some(sub_trie) = trie.subtrie(elected)
             && sub_trie.is_leaf()
         {
             Cow::Owned(elected.to_string() + " ")
@@ -83,7 +75,9 @@ impl Hinter for MyCompleter {
 impl Highlighter for MyCompleter {} // 空实现
 
 impl Validator for MyCompleter {
+    /// 每一行都交给 shell：rustyline 不能为后续行显示提示符，
+    /// 未结束的命令由主循环以 PS2 提示继续读取
     fn validate(&self, _ctx: &mut ValidationContext) -> Result<ValidationResult, ReadlineError> {
-        Ok(ValidationResult::Valid(None)) // 始终认为输入合法
+        Ok(ValidationResult::Valid(None))
     }
 }
@@ -0,0 +1,37 @@
+use super::prelude::*;
+/// Bg命令处理器
+pub struct BgCommand;
+
+impl Builtin for BgCommand {
+    fn execute(&self, params: Vec<String>, context: &mut ExecutionContext) -> BuiltinCommandResult {
+        let jobs = &mut context.state.jobs;
+        if !jobs.job_control() {
+            return BuiltinCommandResult::new_with_stderr("bg: no job control\n".to_string());
+        }
+        let specs: Vec<Option<&str>> = if params.is_empty() {
+            vec![None]
+        } else {
+            params.iter().map(|spec| Some(spec.as_str())).collect()
+        };
+        let mut result = BuiltinCommandResult::default();
+        for spec in specs {
+            let outcome = jobs
+                .resolve(spec)
+                .and_then(|id| jobs.background(id).map(|_| id).map_err(|e| e.to_string()));
+            match outcome {
+                Ok(id) => {
+                    if let Some(job) = jobs.get(id) {
+                        let line =
+                            format!("[{}]{} {}\n", id, jobs.marker(id), job.display_command());
+                        result.stdout.extend(line.bytes());
+                    }
+                }
+                Err(e) => {
+                    result.stderr.extend(format!("bg: {}\n", e).bytes());
+                    result.exit_code = 1;
+                }
+            }
+        }
+        result
+    }
+}
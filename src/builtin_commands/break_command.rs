@@ -0,0 +1,59 @@
+use super::prelude::*;
+use crate::state::Flow;
+/// Break命令处理器
+pub struct BreakCommand;
+
+impl Builtin for BreakCommand {
+    fn execute(&self, params: Vec<String>, context: &mut ExecutionContext) -> BuiltinCommandResult {
+        match loop_count("break", &params, context) {
+            Ok(n) => {
+                context.state.flow = Some(Flow::Break(n));
+                BuiltinCommandResult::default()
+            }
+            Err(result) => result,
+        }
+    }
+}
+
+/// break / continue 的循环层数，超过实际层数时作用于最外层循环
+pub(super) fn loop_count(
+    command: &str,
+    params: &[String],
+    context: &ExecutionContext,
+) -> Result<usize, BuiltinCommandResult> {
+    if context.state.loop_depth == 0 {
+        return Err(BuiltinCommandResult {
+            stderr: format!(
+                "{}: only meaningful in a `for', `while', or `until' loop\n",
+                command
+            )
+            .into_bytes(),
+            ..Default::default()
+        });
+    }
+    let n = match params {
+        [] => 1,
+        [count] => match count.parse::<i64>() {
+            Ok(n) if n >= 1 => n as usize,
+            Ok(_) => {
+                return Err(BuiltinCommandResult::new_with_stderr(format!(
+                    "{}: {}: loop count out of range\n",
+                    command, count
+                )));
+            }
+            Err(_) => {
+                return Err(BuiltinCommandResult::new_with_stderr(format!(
+                    "{}: {}: numeric argument required\n",
+                    command, count
+                )));
+            }
+        },
+        _ => {
+            return Err(BuiltinCommandResult::new_with_stderr(format!(
+                "{}: too many arguments\n",
+                command
+            )));
+        }
+    };
+    Ok(n.min(context.state.loop_depth))
+}
@@ -0,0 +1,17 @@
+use super::break_command::loop_count;
+use super::prelude::*;
+use crate::state::Flow;
+/// Continue命令处理器
+pub struct ContinueCommand;
+
+impl Builtin for ContinueCommand {
+    fn execute(&self, params: Vec<String>, context: &mut ExecutionContext) -> BuiltinCommandResult {
+        match loop_count("continue", &params, context) {
+            Ok(n) => {
+                context.state.flow = Some(Flow::Continue(n));
+                BuiltinCommandResult::default()
+            }
+            Err(result) => result,
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use super::prelude::*;
+use crate::variables::{declare_line, is_valid_name};
+/// Declare命令处理器
+pub struct DeclareCommand;
+
+impl Builtin for DeclareCommand {
+    fn execute(&self, params: Vec<String>, context: &mut ExecutionContext) -> BuiltinCommandResult {
+        let mut exported = None;
+        let mut print = false;
+        let mut names = Vec::new();
+        for param in params {
+            match param.as_str() {
+                "-x" => exported = Some(true),
+                "+x" => exported = Some(false),
+                "-p" => print = true,
+                flag if names.is_empty() && flag.len() > 1 && flag.starts_with(['-', '+']) => {
+                    return BuiltinCommandResult::new_with_stderr(format!(
+                        "declare: {}: invalid option\n",
+                        flag
+                    ));
+                }
+                _ => names.push(param),
+            }
+        }
+
+        if names.is_empty() {
+            return BuiltinCommandResult::new_with_stdout(list_variables(
+                context,
+                exported == Some(true),
+            ));
+        }
+        if print {
+            let mut result = BuiltinCommandResult::default();
+            for name in names {
+                match context.state.vars.get_variable(&name) {
+                    Some(var) => result.stdout.extend(declare_line(&name, var).bytes()),
+                    None => {
+                        result
+                            .stderr
+                            .extend(format!("declare: {}: not found\n", name).bytes());
+                        result.exit_code = 1;
+                    }
+                }
+            }
+            return result;
+        }
+        apply_declarations("declare", &names, exported, context)
+    }
+}
+
+/// 列出变量，only_exported 时只列出导出的变量
+pub(super) fn list_variables(context: &ExecutionContext, only_exported: bool) -> String {
+    context
+        .state
+        .vars
+        .sorted()
+        .into_iter()
+        .filter(|(_, var)| !only_exported || var.exported)
+        .map(|(name, var)| declare_line(name, var))
+        .collect()
+}
+
+/// 处理 NAME[=value] 形式的参数，exported 为 Some 时同时修改导出属性
+pub(super) fn apply_declarations(
+    command: &str,
+    names: &[String],
+    exported: Option<bool>,
+    context: &mut ExecutionContext,
+) -> BuiltinCommandResult {
+    let mut result = BuiltinCommandResult::default();
+    for arg in names {
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (arg.as_str(), None),
+        };
+        if !is_valid_name(name) {
+            result
+                .stderr
+                .extend(format!("{}: `{}': not a valid identifier\n", command, arg).bytes());
+            result.exit_code = 1;
+            continue;
+        }
+        match (exported, value) {
+            (Some(exported), value) => context.state.vars.export(name, value, exported),
+            (None, Some(value)) => context.state.vars.set(name, value),
+            (None, None) => {}
+        }
+    }
+    result
+}
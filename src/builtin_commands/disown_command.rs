@@ -0,0 +1,34 @@
+use super::prelude::*;
+/// Disown命令处理器
+pub struct DisownCommand;
+
+impl Builtin for DisownCommand {
+    fn execute(&self, params: Vec<String>, context: &mut ExecutionContext) -> BuiltinCommandResult {
+        let jobs = &mut context.state.jobs;
+        if params.iter().any(|param| param == "-a") {
+            for id in jobs.ids() {
+                jobs.remove(id);
+            }
+            return BuiltinCommandResult::default();
+        }
+
+        let specs: Vec<Option<&str>> = if params.is_empty() {
+            vec![None]
+        } else {
+            params.iter().map(|spec| Some(spec.as_str())).collect()
+        };
+        let mut result = BuiltinCommandResult::default();
+        for spec in specs {
+            match jobs.resolve(spec) {
+                Ok(id) => {
+                    jobs.remove(id);
+                }
+                Err(e) => {
+                    result.stderr.extend(format!("disown: {}\n", e).bytes());
+                    result.exit_code = 1;
+                }
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,66 @@
+use std::os::unix::process::CommandExt;
+
+use super::prelude::*;
+use crate::{fd_table::install_fds, jobs::reset_signals};
+/// Exec命令处理器
+pub struct ExecCommand;
+
+impl Builtin for ExecCommand {
+    fn execute(&self, params: Vec<String>, context: &mut ExecutionContext) -> BuiltinCommandResult {
+        let fds = context.fds.raw_fds();
+        let Some((name, args)) = params.split_first() else {
+            // 没有命令时，重定向作用于 shell 自身
+            return match install_fds(&fds) {
+                Ok(()) => BuiltinCommandResult::default(),
+                Err(e) => BuiltinCommandResult::new_with_stderr(format!("exec: {}\n", e)),
+            };
+        };
+
+        let Some(path) = context.state.command_cache().lookup(name) else {
+            return exec_failed(
+                BuiltinCommandResult {
+                    stderr: format!("exec: {}: not found\n", name).into_bytes(),
+                    exit_code: 127,
+                    ..Default::default()
+                },
+                context,
+            );
+        };
+        let mut cmd = std::process::Command::new(path);
+        cmd.arg0(name).args(args);
+        cmd.env_clear().envs(context.state.vars.exported());
+        // 被替换的程序不应继承 shell 忽略的信号
+        let reset = context.state.jobs.job_control();
+        unsafe {
+            cmd.pre_exec(move || {
+                if reset {
+                    reset_signals();
+                }
+                install_fds(&fds)
+            });
+        }
+        // exec 成功时不会返回
+        let err = cmd.exec();
+        exec_failed(
+            BuiltinCommandResult {
+                stderr: format!("exec: {}: {}\n", name, crate::utils::strip_os_error(&err))
+                    .into_bytes(),
+                exit_code: 126,
+                ..Default::default()
+            },
+            context,
+        )
+    }
+}
+
+/// exec 无法执行命令时，非交互式 shell 报告错误后以该状态退出
+fn exec_failed(
+    result: BuiltinCommandResult,
+    context: &mut ExecutionContext,
+) -> BuiltinCommandResult {
+    if context.state.interactive {
+        return result;
+    }
+    let result = crate::executor::handler_middle_result(result, context);
+    crate::parse::exit_shell(context, result.exit_code);
+}
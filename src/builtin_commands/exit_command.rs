@@ -3,18 +3,36 @@ use super::prelude::*;
 pub struct ExitCommand;
 
 impl Builtin for ExitCommand {
-    fn execute(
-        &self,
-        _params: Vec<String>,
-        _context: &mut ExecutionContext,
-    ) -> BuiltinCommandResult {
-        match crate::history::write_history_file(_context.rl) {
-            Ok(_) => {
-                std::process::exit(0);
-            }
-            Err(_) => {
-                std::process::exit(0);
+    fn execute(&self, params: Vec<String>, context: &mut ExecutionContext) -> BuiltinCommandResult {
+        // 不带参数时沿用上一条命令的状态
+        let code = match params.as_slice() {
+            [] => context.state.last_status,
+            [code] => match code.parse::<i64>() {
+                Ok(code) => code as i32,
+                Err(_) => {
+                    // 与其他内置命令一样经 fd 表报告错误，然后以状态 2 退出
+                    let result = BuiltinCommandResult {
+                        stderr: format!("exit: {}: numeric argument required\n", code).into_bytes(),
+                        exit_code: 2,
+                        ..Default::default()
+                    };
+                    let result = crate::executor::handler_middle_result(result, context);
+                    crate::parse::exit_shell(context, result.exit_code);
+                }
+            },
+            _ => {
+                return BuiltinCommandResult::new_with_stderr(
+                    "exit: too many arguments\n".to_string(),
+                );
             }
+        };
+
+        // 有停止的作业时先提示一次
+        if !context.state.exit_warned && context.state.jobs.has_stopped() {
+            context.state.exit_warned = true;
+            return BuiltinCommandResult::new_with_stderr("There are stopped jobs.\n".to_string());
         }
+
+        crate::parse::exit_shell(context, code);
     }
 }
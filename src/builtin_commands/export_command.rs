@@ -0,0 +1,30 @@
+use super::declare_command::{apply_declarations, list_variables};
+use super::prelude::*;
+/// Export命令处理器
+pub struct ExportCommand;
+
+impl Builtin for ExportCommand {
+    fn execute(&self, params: Vec<String>, context: &mut ExecutionContext) -> BuiltinCommandResult {
+        let mut exported = true;
+        let mut names = Vec::new();
+        for param in params {
+            match param.as_str() {
+                "-p" => {}
+                "-n" => exported = false,
+                flag if names.is_empty() && flag.len() > 1 && flag.starts_with('-') => {
+                    return BuiltinCommandResult::new_with_stderr(format!(
+                        "export: {}: invalid option\n",
+                        flag
+                    ));
+                }
+                _ => names.push(param),
+            }
+        }
+
+        // export 或 export -p：列出所有导出的变量
+        if names.is_empty() {
+            return BuiltinCommandResult::new_with_stdout(list_variables(context, true));
+        }
+        apply_declarations("export", &names, Some(exported), context)
+    }
+}
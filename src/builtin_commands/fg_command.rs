@@ -0,0 +1,29 @@
+use super::prelude::*;
+/// Fg命令处理器
+pub struct FgCommand;
+
+impl Builtin for FgCommand {
+    fn execute(&self, params: Vec<String>, context: &mut ExecutionContext) -> BuiltinCommandResult {
+        let jobs = &mut context.state.jobs;
+        if !jobs.job_control() {
+            return BuiltinCommandResult::new_with_stderr("fg: no job control\n".to_string());
+        }
+        let id = match jobs.resolve(params.first().map(String::as_str)) {
+            Ok(id) => id,
+            Err(e) => return BuiltinCommandResult::new_with_stderr(format!("fg: {}\n", e)),
+        };
+        // 先显示命令，再把作业放到前台等待
+        if let Some(job) = jobs.get(id) {
+            let _ = context
+                .fds
+                .write_all(1, format!("{}\n", job.command).as_bytes());
+        }
+        match context.state.jobs.foreground(id) {
+            Ok(code) => BuiltinCommandResult {
+                exit_code: code,
+                ..Default::default()
+            },
+            Err(e) => BuiltinCommandResult::new_with_stderr(format!("fg: {}\n", e)),
+        }
+    }
+}
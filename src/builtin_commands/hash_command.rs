@@ -0,0 +1,89 @@
+use std::path::PathBuf;
+
+use super::prelude::*;
+/// Hash命令处理器
+pub struct HashCommand;
+
+impl Builtin for HashCommand {
+    fn execute(&self, params: Vec<String>, context: &mut ExecutionContext) -> BuiltinCommandResult {
+        let mut cache = context.state.command_cache();
+        let mut params = params.iter();
+        let mut result = BuiltinCommandResult::default();
+        let mut names = Vec::new();
+        let mut delete = false;
+        let mut print_path = false;
+        let mut modified = false; // -r / -p 修改了 hash 表时不再列出
+        while let Some(param) = params.next() {
+            match param.as_str() {
+                "-r" => {
+                    cache.clear();
+                    modified = true;
+                }
+                "-d" => delete = true,
+                "-t" => print_path = true,
+                "-p" => {
+                    let (Some(path), Some(name)) = (params.next(), params.next()) else {
+                        return BuiltinCommandResult::new_with_stderr(
+                            "hash: -p: option requires an argument\n".to_string(),
+                        );
+                    };
+                    cache.insert(name, PathBuf::from(path));
+                    modified = true;
+                }
+                flag if flag.len() > 1 && flag.starts_with('-') => {
+                    return BuiltinCommandResult::new_with_stderr(format!(
+                        "hash: {}: invalid option\n",
+                        flag
+                    ));
+                }
+                _ => names.push(param),
+            }
+        }
+
+        if names.is_empty() && !delete && !print_path && !modified {
+            let mut entries = cache.entries().peekable();
+            if entries.peek().is_none() {
+                return BuiltinCommandResult::new_with_stdout(
+                    "hash: hash table empty\n".to_string(),
+                );
+            }
+            let mut table = String::from("hits\tcommand\n");
+            for (_, entry) in entries {
+                table.push_str(&format!("{:>4}\t{}\n", entry.hits, entry.path.display()));
+            }
+            return BuiltinCommandResult::new_with_stdout(table);
+        }
+
+        for name in names {
+            let found = if delete {
+                cache.remove(name)
+            } else if print_path {
+                match cache.hashed(name) {
+                    Some(entry) => {
+                        let line = format!("{}\n", entry.path.display());
+                        result.stdout.extend(line.bytes());
+                        true
+                    }
+                    None => false,
+                }
+            } else {
+                // 显式 hash 时重新查找并将命中次数清零
+                cache.remove(name);
+                match cache.find(name) {
+                    Some(path) => {
+                        cache.insert(name, path);
+                        true
+                    }
+                    None => false,
+                }
+            };
+            if !found {
+                result
+                    .stderr
+                    .extend(format!("hash: {}: not found\n", name).bytes());
+                result.exit_code = 1;
+            }
+        }
+        result
+    }
+}
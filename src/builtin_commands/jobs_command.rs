@@ -0,0 +1,54 @@
+use super::prelude::*;
+/// Jobs命令处理器
+pub struct JobsCommand;
+
+impl Builtin for JobsCommand {
+    fn execute(&self, params: Vec<String>, context: &mut ExecutionContext) -> BuiltinCommandResult {
+        let mut long = false;
+        let mut pids_only = false;
+        let mut specs = Vec::new();
+        for param in &params {
+            match param.as_str() {
+                "-l" => long = true,
+                "-p" => pids_only = true,
+                flag if flag.len() > 1 && flag.starts_with('-') => {
+                    return BuiltinCommandResult::new_with_stderr(format!(
+                        "jobs: {}: invalid option\n",
+                        flag
+                    ));
+                }
+                spec => specs.push(spec),
+            }
+        }
+
+        let jobs = &mut context.state.jobs;
+        jobs.update();
+        let mut result = BuiltinCommandResult::default();
+        let ids = if specs.is_empty() {
+            jobs.ids()
+        } else {
+            let mut ids = Vec::new();
+            for spec in specs {
+                match jobs.resolve(Some(spec)) {
+                    Ok(id) => ids.push(id),
+                    Err(e) => {
+                        result.stderr.extend(format!("jobs: {}\n", e).bytes());
+                        result.exit_code = 1;
+                    }
+                }
+            }
+            ids
+        };
+        for id in ids {
+            let line = match jobs.get(id) {
+                Some(job) if pids_only => format!("{}\n", job.pgid),
+                Some(_) => jobs.format_job(id, long),
+                None => continue,
+            };
+            result.stdout.extend(line.bytes());
+        }
+        // 已结束的作业列出后即被移除
+        jobs.remove_done();
+        result
+    }
+}
@@ -0,0 +1,30 @@
+use super::prelude::*;
+use crate::variables::is_valid_name;
+/// Local命令处理器
+pub struct LocalCommand;
+
+impl Builtin for LocalCommand {
+    fn execute(&self, params: Vec<String>, context: &mut ExecutionContext) -> BuiltinCommandResult {
+        if context.state.function_depth == 0 {
+            return BuiltinCommandResult::new_with_stderr(
+                "local: can only be used in a function\n".to_string(),
+            );
+        }
+        let mut result = BuiltinCommandResult::default();
+        for arg in &params {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (arg.as_str(), None),
+            };
+            if !is_valid_name(name) {
+                result
+                    .stderr
+                    .extend(format!("local: `{}': not a valid identifier\n", arg).bytes());
+                result.exit_code = 1;
+                continue;
+            }
+            context.state.vars.make_local(name, value);
+        }
+        result
+    }
+}
@@ -1,18 +1,52 @@
 use crate::parse::ExecutionContext;
+mod bg_command;
+mod break_command;
 mod cd_command;
+mod continue_command;
+mod declare_command;
+mod disown_command;
 mod echo_command;
+mod exec_command;
 mod exit_command;
+mod export_command;
+mod fg_command;
+mod hash_command;
 mod history_command;
+mod jobs_command;
+mod local_command;
 mod prelude;
 mod pwd_command;
+mod return_command;
+mod set_command;
+mod shopt_command;
+mod source_command;
 mod type_command;
+mod unset_command;
+mod wait_command;
+pub use bg_command::BgCommand;
+pub use break_command::BreakCommand;
 pub use cd_command::CdCommand;
+pub use continue_command::ContinueCommand;
+pub use declare_command::DeclareCommand;
+pub use disown_command::DisownCommand;
 pub use echo_command::EchoCommand;
+pub use exec_command::ExecCommand;
 pub use exit_command::ExitCommand;
+pub use export_command::ExportCommand;
+pub use fg_command::FgCommand;
+pub use hash_command::HashCommand;
 pub use history_command::HistoryCommand;
+pub use jobs_command::JobsCommand;
+pub use local_command::LocalCommand;
 pub use pwd_command::PwdCommand;
+pub use return_command::ReturnCommand;
+pub use set_command::SetCommand;
+pub use shopt_command::ShoptCommand;
+pub use source_command::SourceCommand;
 use strum::{AsRefStr, Display, EnumIter, EnumString};
 pub use type_command::TypeCommand;
+pub use unset_command::UnsetCommand;
+pub use wait_command::WaitCommand;
 /// 内置命令接口
 pub trait Builtin {
     fn execute(&self, params: Vec<String>, context: &mut ExecutionContext) -> BuiltinCommandResult;
@@ -26,6 +60,24 @@ pub enum BuiltinCommand {
     Echo,
     Type,
     History,
+    Export,
+    Unset,
+    Declare,
+    Hash,
+    Shopt,
+    Exec,
+    Set,
+    Jobs,
+    Fg,
+    Bg,
+    Wait,
+    Disown,
+    #[strum(to_string = "source", serialize = ".")]
+    Source,
+    Return,
+    Local,
+    Break,
+    Continue,
 }
 
 /// 表示一个命令执行结果
@@ -65,6 +117,23 @@ impl BuiltinFactory {
             Ok(BuiltinCommand::Pwd) => Some(Box::new(PwdCommand)),
             Ok(BuiltinCommand::Cd) => Some(Box::new(CdCommand)),
             Ok(BuiltinCommand::History) => Some(Box::new(HistoryCommand)),
+            Ok(BuiltinCommand::Export) => Some(Box::new(ExportCommand)),
+            Ok(BuiltinCommand::Unset) => Some(Box::new(UnsetCommand)),
+            Ok(BuiltinCommand::Declare) => Some(Box::new(DeclareCommand)),
+            Ok(BuiltinCommand::Hash) => Some(Box::new(HashCommand)),
+            Ok(BuiltinCommand::Shopt) => Some(Box::new(ShoptCommand)),
+            Ok(BuiltinCommand::Exec) => Some(Box::new(ExecCommand)),
+            Ok(BuiltinCommand::Set) => Some(Box::new(SetCommand)),
+            Ok(BuiltinCommand::Jobs) => Some(Box::new(JobsCommand)),
+            Ok(BuiltinCommand::Fg) => Some(Box::new(FgCommand)),
+            Ok(BuiltinCommand::Bg) => Some(Box::new(BgCommand)),
+            Ok(BuiltinCommand::Wait) => Some(Box::new(WaitCommand)),
+            Ok(BuiltinCommand::Disown) => Some(Box::new(DisownCommand)),
+            Ok(BuiltinCommand::Source) => Some(Box::new(SourceCommand)),
+            Ok(BuiltinCommand::Return) => Some(Box::new(ReturnCommand)),
+            Ok(BuiltinCommand::Local) => Some(Box::new(LocalCommand)),
+            Ok(BuiltinCommand::Break) => Some(Box::new(BreakCommand)),
+            Ok(BuiltinCommand::Continue) => Some(Box::new(ContinueCommand)),
             _ => None,
         }
     }
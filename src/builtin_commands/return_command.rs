@@ -0,0 +1,34 @@
+use super::prelude::*;
+use crate::state::Flow;
+/// Return命令处理器
+pub struct ReturnCommand;
+
+impl Builtin for ReturnCommand {
+    fn execute(&self, params: Vec<String>, context: &mut ExecutionContext) -> BuiltinCommandResult {
+        if context.state.function_depth == 0 && context.state.source_depth == 0 {
+            return BuiltinCommandResult::new_with_stderr(
+                "return: can only `return' from a function or sourced script\n".to_string(),
+            );
+        }
+        // 不带参数时沿用上一条命令的状态
+        let mut result = BuiltinCommandResult::default();
+        match params.as_slice() {
+            [] => result.exit_code = context.state.last_status,
+            [code] => match code.parse::<i64>() {
+                Ok(code) => result.exit_code = (code & 0xff) as i32,
+                Err(_) => {
+                    result.stderr =
+                        format!("return: {}: numeric argument required\n", code).into_bytes();
+                    result.exit_code = 2;
+                }
+            },
+            _ => {
+                return BuiltinCommandResult::new_with_stderr(
+                    "return: too many arguments\n".to_string(),
+                );
+            }
+        }
+        context.state.flow = Some(Flow::Return);
+        result
+    }
+}
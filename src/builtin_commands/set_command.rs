@@ -0,0 +1,97 @@
+use super::prelude::*;
+use crate::options::SET_OPTIONS;
+/// Set命令处理器
+pub struct SetCommand;
+
+impl Builtin for SetCommand {
+    fn execute(&self, params: Vec<String>, context: &mut ExecutionContext) -> BuiltinCommandResult {
+        // 不带参数时列出全部变量
+        if params.is_empty() {
+            let listing: String = context
+                .state
+                .vars
+                .sorted()
+                .into_iter()
+                .filter_map(|(name, var)| Some(format!("{}={}\n", name, var.value.as_ref()?)))
+                .collect();
+            return BuiltinCommandResult::new_with_stdout(listing);
+        }
+
+        let mut result = BuiltinCommandResult::default();
+        let mut params = params.into_iter().peekable();
+        while let Some(param) = params.next_if(|p| p.starts_with(['-', '+'])) {
+            let value = param.starts_with('-');
+            match &param[1..] {
+                "" | "-" => {
+                    // set -- args：后面的参数全部作为位置参数
+                    if param == "--" {
+                        context.state.positional.clear();
+                    }
+                    break;
+                }
+                flags => {
+                    for flag in flags.chars() {
+                        // 选项组中的 o 与 cli 一样取下一个参数作为选项名，如 set -euo pipefail
+                        if flag == 'o' {
+                            let Some(name) = params.next() else {
+                                // 不带选项名时列出全部选项
+                                result.stdout.extend(list_options(value, context).bytes());
+                                continue;
+                            };
+                            match context.state.options.set_o_mut(&name) {
+                                Some(option) => *option = value,
+                                None => {
+                                    return BuiltinCommandResult {
+                                        stderr: format!("set: {}: invalid option name\n", name)
+                                            .into_bytes(),
+                                        exit_code: 2,
+                                        ..Default::default()
+                                    };
+                                }
+                            }
+                            continue;
+                        }
+                        match context.state.options.set_flag_mut(flag) {
+                            Some(option) => *option = value,
+                            None => {
+                                return BuiltinCommandResult {
+                                    stderr: format!(
+                                        "set: {}{}: invalid option\n",
+                                        &param[..1],
+                                        flag
+                                    )
+                                    .into_bytes(),
+                                    exit_code: 2,
+                                    ..Default::default()
+                                };
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        let rest: Vec<String> = params.collect();
+        if !rest.is_empty() {
+            context.state.positional = rest;
+        }
+        result
+    }
+}
+
+/// set -o 列出全部选项的状态，set +o 以可重新执行的命令形式输出
+fn list_options(value: bool, context: &ExecutionContext) -> String {
+    let mut text = String::new();
+    for (name, _) in SET_OPTIONS {
+        let on = context.state.options.set_o(name) == Some(true);
+        if value {
+            text.push_str(&format!(
+                "{:<15}\t{}\n",
+                name,
+                if on { "on" } else { "off" }
+            ));
+        } else {
+            text.push_str(&format!("set {}o {}\n", if on { '-' } else { '+' }, name));
+        }
+    }
+    text
+}
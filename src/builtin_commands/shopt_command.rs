@@ -0,0 +1,80 @@
+use super::prelude::*;
+use crate::options::SHOPT_NAMES;
+/// Shopt命令处理器
+pub struct ShoptCommand;
+
+impl Builtin for ShoptCommand {
+    fn execute(&self, params: Vec<String>, context: &mut ExecutionContext) -> BuiltinCommandResult {
+        let mut set = None;
+        let mut quiet = false;
+        let mut print = false;
+        let mut names = Vec::new();
+        for param in &params {
+            match param.as_str() {
+                "-s" => set = Some(true),
+                "-u" => set = Some(false),
+                "-q" => quiet = true,
+                "-p" => print = true,
+                flag if flag.len() > 1 && flag.starts_with('-') => {
+                    return BuiltinCommandResult::new_with_stderr(format!(
+                        "shopt: {}: invalid option\n",
+                        flag
+                    ));
+                }
+                _ => names.push(param.as_str()),
+            }
+        }
+
+        let options = &mut context.state.options;
+        let mut result = BuiltinCommandResult::default();
+        if let (Some(value), false) = (set, names.is_empty()) {
+            for name in names {
+                match options.shopt_mut(name) {
+                    Some(option) => *option = value,
+                    None => invalid_name(&mut result, name),
+                }
+            }
+            return result;
+        }
+
+        // 未指定名称时列出全部（-s/-u 时只列出对应状态的选项）
+        let listed: Vec<&str> = if names.is_empty() {
+            SHOPT_NAMES
+                .into_iter()
+                .filter(|name| set.is_none_or(|value| options.shopt(name) == Some(value)))
+                .collect()
+        } else {
+            names
+        };
+        for name in listed {
+            match options.shopt(name) {
+                Some(value) => {
+                    if !value {
+                        result.exit_code = 1;
+                    }
+                    if quiet {
+                        continue;
+                    }
+                    let line = if print {
+                        format!("shopt {} {}\n", if value { "-s" } else { "-u" }, name)
+                    } else {
+                        format!("{:<15}\t{}\n", name, if value { "on" } else { "off" })
+                    };
+                    result.stdout.extend(line.bytes());
+                }
+                None => invalid_name(&mut result, name),
+            }
+        }
+        if set.is_some() {
+            result.exit_code = 0;
+        }
+        result
+    }
+}
+
+fn invalid_name(result: &mut BuiltinCommandResult, name: &str) {
+    result
+        .stderr
+        .extend(format!("shopt: {}: invalid shell option name\n", name).bytes());
+    result.exit_code = 1;
+}
@@ -0,0 +1,65 @@
+use std::path::{Path, PathBuf};
+
+use super::prelude::*;
+use crate::{
+    script::{LineSource, run_script},
+    state::Flow,
+};
+/// Source命令处理器，也可写作 .
+pub struct SourceCommand;
+
+impl Builtin for SourceCommand {
+    fn execute(&self, params: Vec<String>, context: &mut ExecutionContext) -> BuiltinCommandResult {
+        let Some((name, args)) = params.split_first() else {
+            return BuiltinCommandResult {
+                stderr: b"source: filename argument required\n".to_vec(),
+                exit_code: 2,
+                ..Default::default()
+            };
+        };
+        let path = find_file(name, context.state.vars.get("PATH").unwrap_or(""));
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) => {
+                return BuiltinCommandResult::new_with_stderr(format!(
+                    "source: {}: {}\n",
+                    name,
+                    crate::utils::strip_os_error(&e)
+                ));
+            }
+        };
+
+        // 有参数时，文件执行期间使用新的位置参数
+        let saved = (!args.is_empty())
+            .then(|| std::mem::replace(&mut context.state.positional, args.to_vec()));
+        context.state.source_depth += 1;
+        let exit_code = run_script(LineSource::from_text(&text), name, false, context);
+        context.state.source_depth -= 1;
+        if let Some(positional) = saved {
+            context.state.positional = positional;
+        }
+        // 文件中的 return 到此为止
+        if context.state.flow == Some(Flow::Return) {
+            context.state.flow = None;
+        }
+        BuiltinCommandResult {
+            exit_code,
+            ..Default::default()
+        }
+    }
+}
+
+/// 不含 / 的文件名先在 PATH 中查找，找不到时使用当前目录下的文件
+fn find_file(name: &str, path_var: &str) -> PathBuf {
+    if !name.contains('/') {
+        let found = path_var
+            .split(':')
+            .filter(|dir| !dir.is_empty())
+            .map(|dir| Path::new(dir).join(name))
+            .find(|path| path.is_file());
+        if let Some(path) = found {
+            return path;
+        }
+    }
+    PathBuf::from(name)
+}
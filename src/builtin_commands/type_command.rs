@@ -1,4 +1,5 @@
 use super::prelude::*;
+use crate::parse::format_function;
 /// Type命令处理器
 pub struct TypeCommand;
 
@@ -6,7 +7,7 @@ impl Builtin for TypeCommand {
     fn execute(
         &self,
         params: Vec<String>,
-        _context: &mut ExecutionContext,
+        context: &mut ExecutionContext,
     ) -> BuiltinCommandResult {
         let mut params = params.iter();
         let command_type = params.next().context("type command is empty");
@@ -15,14 +16,21 @@ impl Builtin for TypeCommand {
             Err(e) => return BuiltinCommandResult::new_with_stderr(e.to_string()),
         };
 
+        if let Some(body) = context.state.functions.get(command_type) {
+            return BuiltinCommandResult::new_with_stdout(format!(
+                "{} is a function\n{}",
+                command_type,
+                format_function(command_type, body)
+            ));
+        }
         match command_type.parse::<BuiltinCommand>() {
             Ok(_) => BuiltinCommandResult::new_with_stdout(format!(
                 "{} is a shell builtin\n",
                 command_type
             )),
             _ => {
-                match crate::utils::find_executable_file_in_paths(command_type, &crate::GLOBAL_VEC)
-                {
+                let file_path = context.state.command_cache().find(command_type);
+                match file_path {
                     Some(file_path) => BuiltinCommandResult::new_with_stdout(format!(
                         "{} is {}\n",
                         command_type,
@@ -0,0 +1,28 @@
+use super::prelude::*;
+use crate::variables::is_valid_name;
+/// Unset命令处理器
+pub struct UnsetCommand;
+
+impl Builtin for UnsetCommand {
+    fn execute(&self, params: Vec<String>, context: &mut ExecutionContext) -> BuiltinCommandResult {
+        let mut functions = false;
+        let mut result = BuiltinCommandResult::default();
+        for param in params {
+            match param.as_str() {
+                "-v" => functions = false,
+                "-f" => functions = true,
+                name if functions => {
+                    context.state.functions.remove(name);
+                }
+                name if is_valid_name(name) => context.state.vars.unset(name),
+                name => {
+                    result
+                        .stderr
+                        .extend(format!("unset: `{}': not a valid identifier\n", name).bytes());
+                    result.exit_code = 1;
+                }
+            }
+        }
+        result
+    }
+}
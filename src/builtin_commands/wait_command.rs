@@ -0,0 +1,55 @@
+use super::prelude::*;
+use crate::jobs::WaitOutcome;
+/// Wait命令处理器
+pub struct WaitCommand;
+
+impl Builtin for WaitCommand {
+    fn execute(&self, params: Vec<String>, context: &mut ExecutionContext) -> BuiltinCommandResult {
+        let jobs = &mut context.state.jobs;
+        let mut result = BuiltinCommandResult::default();
+        // 不带参数时等待全部作业，跳过停止的作业
+        if params.is_empty() {
+            for id in jobs.ids() {
+                match jobs.wait_job(id) {
+                    WaitOutcome::Done(_) => {}
+                    WaitOutcome::Stopped => {
+                        let message = format!("wait: job {} stopped\n", id);
+                        result.stderr.extend(message.bytes());
+                    }
+                    WaitOutcome::Interrupted => {
+                        result.exit_code = 128 + libc::SIGINT;
+                        break;
+                    }
+                }
+            }
+            return result;
+        }
+
+        for spec in &params {
+            match jobs.resolve(Some(spec)) {
+                Ok(id) => match jobs.wait_job(id) {
+                    WaitOutcome::Done(code) => result.exit_code = code,
+                    WaitOutcome::Stopped => {
+                        let message = format!("wait: job {} stopped\n", id);
+                        result.stderr.extend(message.bytes());
+                        result.exit_code = 128 + libc::SIGTSTP;
+                    }
+                    WaitOutcome::Interrupted => {
+                        result.exit_code = 128 + libc::SIGINT;
+                        break;
+                    }
+                },
+                Err(_) => {
+                    let message = if spec.starts_with('%') {
+                        format!("wait: {}: no such job\n", spec)
+                    } else {
+                        format!("wait: pid {} is not a child of this shell\n", spec)
+                    };
+                    result.stderr.extend(message.bytes());
+                    result.exit_code = 127;
+                }
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{options::SET_OPTIONS, state::ShellState};
+
+/// 命令行参数
+#[derive(Debug, Default)]
+pub struct CliArgs {
+    pub command: Option<String>,                // -c 的命令字符串
+    pub script: Option<String>,                 // 脚本文件
+    pub args: Vec<String>,                      // 位置参数
+    pub read_stdin: bool,                       // -s：从标准输入读取命令
+    pub noexec: bool,                           // -n：只做语法检查
+    pub interactive: bool,                      // -i：强制交互模式
+    pub login: bool,                            // -l / --login，或 argv[0] 以 - 开头
+    pub norc: bool,                             // --norc：不读取 rc 文件
+    pub noprofile: bool,                        // --noprofile：不读取 profile
+    pub set_options: Vec<(&'static str, bool)>, // -e、-o name 等 set 选项
+}
+
+/// 解析命令行参数（含 argv[0]）：[--login] [--norc] [--noprofile] [-cilnsx...] [-o option]
+/// [command_string | script] [args...]
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> anyhow::Result<CliArgs> {
+    let mut cli = CliArgs::default();
+    let mut use_command = false;
+    let mut args = args.into_iter().peekable();
+    // 登录 shell 的 argv[0] 以 - 开头
+    cli.login = args.next().is_some_and(|arg0| arg0.starts_with('-'));
+    while let Some(arg) = args.next_if(|arg| arg.len() > 1 && arg.starts_with(['-', '+'])) {
+        match arg.as_str() {
+            "--" => break,
+            "--login" => cli.login = true,
+            "--norc" => cli.norc = true,
+            "--noprofile" => cli.noprofile = true,
+            long if long.starts_with("--") => anyhow::bail!("{}: invalid option", long),
+            _ => {}
+        }
+        if arg.starts_with("--") {
+            continue;
+        }
+        let value = arg.starts_with('-');
+        for flag in arg[1..].chars() {
+            match flag {
+                'c' if value => use_command = true,
+                's' if value => cli.read_stdin = true,
+                'i' if value => cli.interactive = true,
+                'l' if value => cli.login = true,
+                'n' if value => cli.noexec = true,
+                'o' => {
+                    let name = args
+                        .next()
+                        .ok_or_else(|| anyhow::anyhow!("-o: option requires an argument"))?;
+                    let option = SET_OPTIONS
+                        .iter()
+                        .find(|(option, _)| *option == name)
+                        .ok_or_else(|| anyhow::anyhow!("{}: invalid option name", name))?;
+                    cli.set_options.push((option.0, value));
+                }
+                _ => {
+                    let option = SET_OPTIONS
+                        .iter()
+                        .find(|(_, f)| *f == Some(flag))
+                        .ok_or_else(|| anyhow::anyhow!("{}{}: invalid option", &arg[..1], flag))?;
+                    cli.set_options.push((option.0, value));
+                }
+            }
+        }
+    }
+
+    let mut rest: Vec<String> = args.collect();
+    if use_command {
+        if rest.is_empty() {
+            anyhow::bail!("-c: option requires an argument");
+        }
+        cli.command = Some(rest.remove(0));
+    } else if !cli.read_stdin && !rest.is_empty() {
+        cli.script = Some(rest.remove(0));
+    }
+    cli.args = rest;
+    Ok(cli)
+}
+
+impl CliArgs {
+    /// 没有命令字符串和脚本、且标准输入输出是终端时进入交互模式
+    pub fn is_interactive(&self) -> bool {
+        self.interactive
+            || (self.command.is_none()
+                && self.script.is_none()
+                && unsafe { libc::isatty(0) == 1 && libc::isatty(2) == 1 })
+    }
+
+    /// 设置 $0、位置参数和 set 选项
+    pub fn apply(&self, state: &mut ShellState) {
+        let mut args = self.args.clone();
+        if let Some(script) = &self.script {
+            state.shell_name = script.clone();
+        } else if self.command.is_some() && !args.is_empty() {
+            // sh -c 'cmd' name args...：第一个参数成为 $0
+            state.shell_name = args.remove(0);
+        }
+        state.positional = args;
+        for (name, value) in &self.set_options {
+            if let Some(option) = state.options.set_o_mut(name) {
+                *option = *value;
+            }
+        }
+    }
+}
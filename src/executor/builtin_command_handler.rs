@@ -18,16 +18,17 @@ impl CommandHandler for BuiltinCommandHandler {
     }
 }
 
-fn handler_middle_result(
+/// 把内置命令的输出写入 fd 表，错误输出加上脚本位置前缀
+pub fn handler_middle_result(
     middle_result: BuiltinCommandResult,
     context: &mut ExecutionContext,
 ) -> CommandResult {
     // 处理标准输出和错误输出
-    if let Some(mut stdout) = context.stdout.take() {
-        let _ = stdout.write_all(&middle_result.stdout);
-    }
-    if let Some(mut stderr) = context.stderr.take() {
-        let _ = stderr.write_all(&middle_result.stderr);
+    let _ = context.fds.write_all(1, &middle_result.stdout);
+    if !middle_result.stderr.is_empty() {
+        let prefix = context.state.error_prefix();
+        let _ = context.fds.write_all(2, prefix.as_bytes());
+        let _ = context.fds.write_all(2, &middle_result.stderr);
     }
     middle_result.into()
 }
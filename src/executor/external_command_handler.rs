@@ -1,3 +1,5 @@
+use std::{io::Read, path::Path};
+
 /// 外部命令处理器
 use super::prelude::*;
 pub struct ExternalCommandHandler;
@@ -9,38 +11,76 @@ impl CommandHandler for ExternalCommandHandler {
         args: Vec<String>,
         context: &mut ExecutionContext,
     ) -> CommandResult {
-        match crate::utils::find_executable_file_in_paths(command, &crate::GLOBAL_VEC) {
+        let file_path = context.state.command_cache().lookup(command);
+        match file_path {
             Some(file_path) => {
-                let file_name = file_path.file_name().context("file name is empty");
-                if file_name.is_err() {
-                    eprintln!("{}: file name is empty\n", command);
-                    return CommandResult::new(1);
-                }
-                let mut cmd = std::process::Command::new(file_name.unwrap());
-                cmd.args(args);
-                // 应用标准输入输出重定向
-                if let Some(stdin) = context.stdin.take() {
-                    cmd.stdin(Stdio::from(stdin));
-                }
-                if let Some(stdout) = context.stdout.take() {
-                    cmd.stdout(Stdio::from(stdout));
+                // 使用查找到的完整路径启动，argv[0] 保持用户输入的命令名
+                let mut cmd = std::process::Command::new(&file_path);
+                cmd.arg0(command).args(args);
+                // 只传递导出的变量
+                cmd.env_clear().envs(context.state.vars.exported());
+                // 在子进程中加入作业的进程组，并按 fd 表 dup2
+                let pgid = context.state.jobs.child_pgid();
+                let fds = context.fds.raw_fds();
+                unsafe {
+                    cmd.pre_exec(move || {
+                        prepare_child(pgid)?;
+                        install_fds(&fds)
+                    });
                 }
-                if let Some(stderr) = context.stderr.take() {
-                    cmd.stderr(Stdio::from(stderr));
-                }
-                let child = cmd.spawn().context("spawn command failed");
-                match child {
-                    Ok(child) => CommandResult::external_with_child(child),
+                match cmd.spawn() {
+                    Ok(child) => {
+                        context.state.jobs.register_child(child.id() as libc::pid_t);
+                        CommandResult::external_with_child(child)
+                    }
                     Err(e) => {
-                        eprintln!("{}: spawn command failed: {:?}\n", command, e);
-                        CommandResult::new(1)
+                        let (reason, code) = spawn_error(&file_path, &e);
+                        let message =
+                            format!("{}{}: {}\n", context.state.error_prefix(), command, reason);
+                        let _ = context.fds.write_all(2, message.as_bytes());
+                        CommandResult::new(code)
                     }
                 }
             }
             None => {
-                eprintln!("{}: command not found", command);
-                CommandResult::new(1)
+                // 找到文件但不可执行时状态为 126，找不到命令时为 127
+                let found = context.state.command_cache().find_non_executable(command);
+                let (reason, code) = match found {
+                    Some(_) => ("Permission denied", 126),
+                    None => ("command not found", 127),
+                };
+                let message = format!("{}{}: {}\n", context.state.error_prefix(), command, reason);
+                let _ = context.fds.write_all(2, message.as_bytes());
+                CommandResult::new(code)
             }
         }
     }
 }
+
+/// 启动失败的原因和状态：命令文件本身不存在时为 127，
+/// 其他情况（如 #! 指定的解释器不存在、没有执行权限）为 126
+fn spawn_error(path: &Path, err: &std::io::Error) -> (String, i32) {
+    let reason = crate::utils::strip_os_error(err);
+    if err.kind() != std::io::ErrorKind::NotFound {
+        return (reason, 126);
+    }
+    if !path.exists() {
+        return (reason, 127);
+    }
+    match interpreter(path) {
+        Some(interpreter) => (format!("{}: bad interpreter: {}", interpreter, reason), 126),
+        None => (reason, 126),
+    }
+}
+
+/// 脚本第一行 #! 指定的解释器
+fn interpreter(path: &Path) -> Option<String> {
+    let mut head = [0; 256];
+    let len = std::fs::File::open(path).ok()?.read(&mut head).ok()?;
+    let line = head[..len]
+        .strip_prefix(b"#!")?
+        .split(|&b| b == b'\n')
+        .next()?;
+    let line = String::from_utf8_lossy(line);
+    line.split_whitespace().next().map(str::to_string)
+}
@@ -0,0 +1,36 @@
+/// 函数调用处理器
+use super::prelude::*;
+use crate::{
+    parse::{execute_command_type, report_error},
+    state::Flow,
+};
+pub struct FunctionCommandHandler;
+
+impl CommandHandler for FunctionCommandHandler {
+    fn execute(
+        &self,
+        command: &str,
+        args: Vec<String>,
+        context: &mut ExecutionContext,
+    ) -> CommandResult {
+        let Some(body) = context.state.functions.get(command).cloned() else {
+            return CommandResult::new(127);
+        };
+        // 调用期间使用自己的位置参数和局部变量，调用上的重定向作用于整个函数体
+        let positional = std::mem::replace(&mut context.state.positional, args);
+        // 函数中的 break 和 continue 不作用于调用者的循环
+        let loop_depth = std::mem::take(&mut context.state.loop_depth);
+        context.state.function_depth += 1;
+        context.state.vars.push_scope();
+        let result = execute_command_type(&body, context);
+        context.state.vars.pop_scope();
+        context.state.function_depth -= 1;
+        context.state.loop_depth = loop_depth;
+        context.state.positional = positional;
+        // 函数体中的 return 到此为止
+        if context.state.flow == Some(Flow::Return) {
+            context.state.flow = None;
+        }
+        report_error(result, context)
+    }
+}
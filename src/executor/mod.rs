@@ -1,11 +1,13 @@
 mod builtin_command_handler;
 mod external_command_handler;
+mod function_command_handler;
 pub mod prelude;
 
 use crate::{
     builtin_commands::BuiltinCommand,
     builtin_commands::BuiltinCommandResult,
     parse::ExecutionContext, // 添加ExecutionContext导入
+    state::ShellState,
 };
 /// 简化的命令处理器接口
 pub trait CommandHandler {
@@ -17,13 +19,19 @@ pub trait CommandHandler {
     ) -> CommandResult;
 }
 
+pub use builtin_command_handler::handler_middle_result;
 use builtin_command_handler::BuiltinCommandHandler;
 use external_command_handler::ExternalCommandHandler;
+use function_command_handler::FunctionCommandHandler;
 /// 命令处理器工厂
 pub struct CommandHandlerFactory;
 
 impl CommandHandlerFactory {
-    pub fn create_handler(command: &str) -> Box<dyn CommandHandler + 'static> {
+    pub fn create_handler(command: &str, state: &ShellState) -> Box<dyn CommandHandler + 'static> {
+        // 函数优先于内置命令和外部命令
+        if state.functions.contains_key(command) {
+            return Box::new(FunctionCommandHandler);
+        }
         match command.parse::<BuiltinCommand>() {
             Ok(_) => Box::new(BuiltinCommandHandler),
             Err(_) => Box::new(ExternalCommandHandler),
@@ -36,7 +44,7 @@ impl CommandHandlerFactory {
 pub struct CommandResult {
     #[allow(dead_code)]
     pub exit_code: i32, // 退出码，0表示成功
-    pub child: Option<std::process::Child>,
+    pub pid: Option<libc::pid_t>, // 需要等待的子进程
 }
 impl Default for CommandResult {
     fn default() -> Self {
@@ -47,13 +55,16 @@ impl CommandResult {
     pub fn new(exit_code: i32) -> Self {
         Self {
             exit_code,
-            child: None,
+            pid: None,
         }
     }
     pub fn external_with_child(child: std::process::Child) -> Self {
+        Self::forked(child.id() as libc::pid_t)
+    }
+    pub fn forked(pid: libc::pid_t) -> Self {
         Self {
             exit_code: 0,
-            child: Some(child),
+            pid: Some(pid),
         }
     }
 }
@@ -62,7 +73,7 @@ impl From<BuiltinCommandResult> for CommandResult {
     fn from(value: BuiltinCommandResult) -> Self {
         Self {
             exit_code: value.exit_code,
-            child: None,
+            pid: None,
         }
     }
 }
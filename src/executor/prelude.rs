@@ -1,11 +1,11 @@
-pub use std::{io::Write, process::Stdio};
-
-pub use anyhow::Context;
+pub use std::{io::Write, os::unix::process::CommandExt};
 
 pub use crate::{
     builtin_commands::BuiltinCommandResult,
     builtin_commands::BuiltinFactory,
     executor::CommandHandler,
     executor::CommandResult,
+    fd_table::install_fds,
+    jobs::prepare_child,
     parse::ExecutionContext, // 添加ExecutionContext导入
 };
@@ -0,0 +1,788 @@
+use std::io::Read;
+
+use crate::{
+    glob::{Pattern, glob},
+    lexer::{Parameter, ParameterOp, ReplaceMode, Word, WordPart},
+    options::ShellOptions,
+    parse::{ExecutionContext, execute_source},
+    state::ShellState,
+    utils::wait_for_pid,
+    variables::is_valid_name,
+};
+
+/// 默认的字段分隔符
+const DEFAULT_IFS: &str = " \t\n";
+
+/// 非交互式 shell 中会使 shell 退出的展开错误
+#[derive(Debug, thiserror::Error)]
+pub enum FatalError {
+    #[error("{0}: unbound variable")]
+    Unbound(String),
+    #[error("{name}: {message}")]
+    NullOrUnset { name: String, message: String }, // ${name:?word}
+}
+
+impl FatalError {
+    /// shell 因此退出时的状态
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            FatalError::Unbound(_) | FatalError::NullOrUnset { .. } => 127,
+        }
+    }
+}
+
+/// 参数展开、命令替换或算术展开的结果
+enum Expansion {
+    Value(String),       // 单个值
+    Fields(Vec<String>), // $@ 和 ${a[@]} 展开为多个字段
+}
+
+impl Expansion {
+    /// 不分词的场合，多个字段以空格连接
+    fn into_text(self) -> String {
+        match self {
+            Expansion::Value(value) => value,
+            Expansion::Fields(values) => values.join(" "),
+        }
+    }
+}
+
+/// 展开结果的字段收集器，逐字符记录是否被引用以便路径名展开
+#[derive(Default)]
+struct Fields {
+    fields: Vec<Vec<(char, bool)>>,
+    current: Vec<(char, bool)>,
+    has_current: bool, // 当前字段是否存在（空引号也构成字段）
+    after_blank: bool, // 上一个字段刚被 IFS 空白结束，紧随的非空白分隔符不再产生空字段
+}
+
+impl Fields {
+    fn push_str(&mut self, s: &str, quoted: bool) {
+        self.current.extend(s.chars().map(|ch| (ch, quoted)));
+        self.has_current = true;
+        self.after_blank = false;
+    }
+
+    /// 追加需要分词的展开结果：连续的 IFS 空白只分隔一次，
+    /// 每个非空白的 IFS 字符都结束一个字段，即使该字段为空
+    fn push_split(&mut self, s: &str, ifs: &str) {
+        for ch in s.chars() {
+            if !ifs.contains(ch) {
+                self.current.push((ch, false));
+                self.has_current = true;
+                self.after_blank = false;
+            } else if ch.is_whitespace() {
+                if self.has_current {
+                    self.finish();
+                    self.after_blank = true;
+                }
+            } else {
+                if !self.after_blank {
+                    self.has_current = true;
+                }
+                self.finish();
+                self.after_blank = false;
+            }
+        }
+    }
+
+    /// 追加展开结果，多个字段各自成为一个字段；ifs 为 None 时（双引号内）不分词
+    fn push_expansion(&mut self, expansion: Expansion, ifs: Option<&str>) {
+        let values = match expansion {
+            Expansion::Value(value) => vec![value],
+            Expansion::Fields(values) => values,
+        };
+        for (i, value) in values.iter().enumerate() {
+            if i > 0 {
+                self.finish();
+            }
+            match ifs {
+                Some(ifs) => self.push_split(value, ifs),
+                None => self.push_str(value, true),
+            }
+        }
+    }
+
+    fn finish(&mut self) {
+        self.after_blank = false;
+        if self.has_current {
+            self.fields.push(std::mem::take(&mut self.current));
+            self.has_current = false;
+        }
+    }
+
+    fn into_fields(mut self) -> Vec<Vec<(char, bool)>> {
+        self.finish();
+        self.fields
+    }
+}
+
+/// 展开多个单词并分词，用于命令参数
+pub fn expand_words(words: &[Word], context: &mut ExecutionContext) -> anyhow::Result<Vec<String>> {
+    let mut fields = Vec::new();
+    for word in words {
+        fields.extend(expand_word_fields(word, context)?);
+    }
+    Ok(fields)
+}
+
+/// 按 POSIX 顺序展开单词：参数展开、命令替换和算术展开，
+/// 然后对未加引号的展开结果按 IFS 分词，最后做路径名展开并去除引号
+pub fn expand_word_fields(
+    word: &Word,
+    context: &mut ExecutionContext,
+) -> anyhow::Result<Vec<String>> {
+    let ifs = context
+        .state
+        .vars
+        .get("IFS")
+        .unwrap_or(DEFAULT_IFS)
+        .to_string();
+    let mut fields = Fields::default();
+    for part in &word.parts {
+        match part {
+            WordPart::Literal(s) => fields.push_str(s, false),
+            WordPart::SingleQuoted(s) => fields.push_str(s, true),
+            WordPart::DoubleQuoted(parts) => {
+                // "" 构成一个空字段，但没有参数时 "$@" 不产生字段
+                if !parts.iter().any(is_multi_field) {
+                    fields.has_current = true;
+                }
+                for part in parts {
+                    match part {
+                        WordPart::Literal(s) => fields.push_str(s, true),
+                        _ => fields.push_expansion(expand_part(part, context)?, None),
+                    }
+                }
+            }
+            // 未加引号的 $* 与 $@ 相同，每个位置参数各自分词
+            WordPart::Parameter(parameter) if is_bare_star(parameter) => {
+                let values = context.state.positional.clone();
+                fields.push_expansion(Expansion::Fields(values), Some(&ifs));
+            }
+            _ => fields.push_expansion(expand_part(part, context)?, Some(&ifs)),
+        }
+    }
+    let mut result = Vec::new();
+    for field in fields.into_fields() {
+        result.extend(pathname_expansion(field, &context.state.options)?);
+    }
+    Ok(result)
+}
+
+/// 片段是否为 $@ 或 ${a[@]}
+fn is_multi_field(part: &WordPart) -> bool {
+    matches!(part, WordPart::Parameter(parameter) if parameter.name == "@" || parameter.name.ends_with("[@]"))
+}
+
+/// 不带操作的 $* 或 ${*}
+fn is_bare_star(parameter: &Parameter) -> bool {
+    parameter.name == "*" && !parameter.indirect && parameter.op.is_none()
+}
+
+/// 路径名展开：只有未被引用的 *、?、[...] 才是通配符
+fn pathname_expansion(
+    field: Vec<(char, bool)>,
+    options: &ShellOptions,
+) -> anyhow::Result<Vec<String>> {
+    let text: String = field.iter().map(|(ch, _)| ch).collect();
+    if Pattern::new(&field).is_literal() {
+        return Ok(vec![text]);
+    }
+    let matches = glob(&field, options.glob_options());
+    if !matches.is_empty() {
+        Ok(matches)
+    } else if options.failglob {
+        anyhow::bail!("no match: {}", text)
+    } else if options.nullglob {
+        Ok(Vec::new())
+    } else {
+        Ok(vec![text])
+    }
+}
+
+/// 展开单词但不分词，用于赋值和重定向目标
+pub fn expand_word(word: &Word, context: &mut ExecutionContext) -> anyhow::Result<String> {
+    expand_text(&word.parts, context)
+}
+
+/// 展开片段序列并连接为一个字符串
+fn expand_text(parts: &[WordPart], context: &mut ExecutionContext) -> anyhow::Result<String> {
+    let mut result = String::new();
+    for part in parts {
+        match part {
+            WordPart::Literal(s) | WordPart::SingleQuoted(s) => result.push_str(s),
+            WordPart::DoubleQuoted(parts) => result.push_str(&expand_text(parts, context)?),
+            _ => result.push_str(&expand_part(part, context)?.into_text()),
+        }
+    }
+    Ok(result)
+}
+
+/// 展开单词为匹配模式，不分词，引号中的字符按字面匹配，用于 case
+pub fn expand_pattern(word: &Word, context: &mut ExecutionContext) -> anyhow::Result<Pattern> {
+    let mut chars = Vec::new();
+    for part in &word.parts {
+        let (text, quoted) = match part {
+            WordPart::Literal(s) => (s.clone(), false),
+            WordPart::SingleQuoted(s) => (s.clone(), true),
+            WordPart::DoubleQuoted(parts) => (expand_text(parts, context)?, true),
+            _ => (expand_part(part, context)?.into_text(), false),
+        };
+        chars.extend(text.chars().map(|ch| (ch, quoted)));
+    }
+    Ok(Pattern::new(&chars))
+}
+
+/// 展开参数、命令替换或算术展开片段
+fn expand_part(part: &WordPart, context: &mut ExecutionContext) -> anyhow::Result<Expansion> {
+    match part {
+        WordPart::Parameter(parameter) => expand_parameter(parameter, context),
+        WordPart::CommandSubst(command) => {
+            Ok(Expansion::Value(command_substitution(command, context)?))
+        }
+        WordPart::Arithmetic(parts) => {
+            let expr = expand_text(parts, context)?;
+            anyhow::bail!("$(({})): arithmetic expansion is not supported", expr)
+        }
+        WordPart::Literal(s) | WordPart::SingleQuoted(s) => Ok(Expansion::Value(s.clone())),
+        WordPart::DoubleQuoted(parts) => Ok(Expansion::Value(expand_text(parts, context)?)),
+    }
+}
+
+/// 命令替换：在子 shell 中执行命令，捕获其标准输出并去掉末尾换行
+fn command_substitution(command: &str, context: &mut ExecutionContext) -> anyhow::Result<String> {
+    let (mut reader, writer) = crate::fd_table::pipe()?;
+
+    match unsafe { libc::fork() } {
+        -1 => Err(std::io::Error::last_os_error().into()),
+        0 => {
+            // 子进程：标准输出写入管道，执行完直接退出
+            drop(reader);
+            context.state.jobs.enter_subshell();
+            let status = context.duplicate().and_then(|mut sub_context| {
+                sub_context.fds.set(1, writer);
+                execute_source(command, &mut sub_context)
+            });
+            let code = match status {
+                Ok(result) => result.exit_code,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    1
+                }
+            };
+            std::process::exit(code);
+        }
+        pid => {
+            drop(writer);
+            let mut output = Vec::new();
+            reader.read_to_end(&mut output)?;
+            let status = wait_for_pid(pid)?;
+            context.state.last_status = status;
+            context.state.subst_status = Some(status);
+            let mut output = String::from_utf8_lossy(&output).into_owned();
+            output.truncate(output.trim_end_matches('\n').len());
+            Ok(output)
+        }
+    }
+}
+
+/// 参数展开：$NAME、${NAME}、特殊参数、${NAME[i]} 以及 ${...} 中的各种操作
+fn expand_parameter(
+    parameter: &Parameter,
+    context: &mut ExecutionContext,
+) -> anyhow::Result<Expansion> {
+    if let Some(ParameterOp::Invalid(text)) = &parameter.op {
+        anyhow::bail!("${{{}}}: bad substitution", text);
+    }
+    // ${!name}：以 name 的值作为参数名
+    let name = if parameter.indirect {
+        match lookup_parameter(&parameter.name, context.state) {
+            Some(name) if !name.is_empty() => name,
+            _ => anyhow::bail!("{}: invalid indirect expansion", parameter.name),
+        }
+    } else {
+        parameter.name.clone()
+    };
+    let value = lookup_expansion(&name, context.state);
+    let op = match &parameter.op {
+        // 按参数是否设置选择结果的操作
+        Some(
+            op @ (ParameterOp::Default { colon, word }
+            | ParameterOp::Assign { colon, word }
+            | ParameterOp::Error { colon, word }
+            | ParameterOp::Alternative { colon, word }),
+        ) => {
+            let value = value.filter(|value| is_set(value, *colon));
+            return expand_conditional(op, value, &name, word, context);
+        }
+        Some(op) => op,
+        None => return value_or_unbound(value, &name, context.state),
+    };
+    if let ParameterOp::Length = op {
+        let length = match value {
+            // ${#*} 和 ${#a[*]} 也是个数
+            _ if name == "*" => context.state.positional.len(),
+            Some(Expansion::Value(_)) if name.ends_with("[*]") => {
+                lookup_expansion(&name.replace("[*]", "[@]"), context.state).map_or(0, |value| {
+                    match value {
+                        Expansion::Fields(values) => values.len(),
+                        Expansion::Value(_) => 1,
+                    }
+                })
+            }
+            Some(Expansion::Fields(values)) => values.len(),
+            value => value_or_unbound(value, &name, context.state)?
+                .into_text()
+                .chars()
+                .count(),
+        };
+        return Ok(Expansion::Value(length.to_string()));
+    }
+    let value = value_or_unbound(value, &name, context.state)?;
+    match op {
+        ParameterOp::Substring { offset, length } => {
+            let offset = parse_integer(&expand_word(offset, context)?)?;
+            let length = match length {
+                Some(length) => Some(parse_integer(&expand_word(length, context)?)?),
+                None => None,
+            };
+            substring_expansion(value, &name, offset, length, context.state)
+        }
+        ParameterOp::RemovePrefix { longest, pattern } => {
+            let pattern = expand_pattern(pattern, context)?;
+            Ok(map_values(value, |value| {
+                let end = prefix_end(value, &pattern, *longest).unwrap_or(0);
+                value[end..].to_string()
+            }))
+        }
+        ParameterOp::RemoveSuffix { longest, pattern } => {
+            let pattern = expand_pattern(pattern, context)?;
+            Ok(map_values(value, |value| {
+                let start = suffix_start(value, &pattern, *longest).unwrap_or(value.len());
+                value[..start].to_string()
+            }))
+        }
+        ParameterOp::Replace {
+            mode,
+            pattern,
+            replacement,
+        } => {
+            let pattern = expand_pattern(pattern, context)?;
+            let replacement = expand_word(replacement, context)?;
+            Ok(map_values(value, |value| {
+                replace(value, &pattern, &replacement, *mode)
+            }))
+        }
+        ParameterOp::UpperCase { all } => Ok(map_values(value, |value| {
+            change_case(value, *all, char::to_uppercase)
+        })),
+        ParameterOp::LowerCase { all } => Ok(map_values(value, |value| {
+            change_case(value, *all, char::to_lowercase)
+        })),
+        _ => unreachable!("conditional operators are handled above"),
+    }
+}
+
+/// 参数是否视为已设置：colon 为真时空值视为未设置，没有位置参数时 $@ 视为未设置
+fn is_set(value: &Expansion, colon: bool) -> bool {
+    match value {
+        Expansion::Value(value) => !(colon && value.is_empty()),
+        Expansion::Fields(values) => !values.is_empty(),
+    }
+}
+
+/// ${name:-word}、${name:=word}、${name:?word} 和 ${name:+word}，value 为 None 表示视为未设置
+fn expand_conditional(
+    op: &ParameterOp,
+    value: Option<Expansion>,
+    name: &str,
+    word: &Word,
+    context: &mut ExecutionContext,
+) -> anyhow::Result<Expansion> {
+    match (op, value) {
+        (ParameterOp::Alternative { .. }, Some(_)) => {
+            Ok(Expansion::Value(expand_word(word, context)?))
+        }
+        (ParameterOp::Alternative { .. }, None) => Ok(Expansion::Value(String::new())),
+        (_, Some(value)) => Ok(value),
+        (ParameterOp::Assign { .. }, None) => {
+            if !is_valid_name(name) {
+                anyhow::bail!("${}: cannot assign in this way", name);
+            }
+            let value = expand_word(word, context)?;
+            context.state.vars.set(name, value.clone());
+            Ok(Expansion::Value(value))
+        }
+        (ParameterOp::Error { colon, .. }, None) => {
+            let mut message = expand_word(word, context)?;
+            if message.is_empty() {
+                message = if *colon {
+                    "parameter null or not set".to_string()
+                } else {
+                    "parameter not set".to_string()
+                };
+            }
+            let name = name.to_string();
+            Err(FatalError::NullOrUnset { name, message }.into())
+        }
+        (_, None) => Ok(Expansion::Value(expand_word(word, context)?)),
+    }
+}
+
+/// 未设置的参数展开为空，set -u 时是错误（$@ 和 $* 除外）
+fn value_or_unbound(
+    value: Option<Expansion>,
+    name: &str,
+    state: &ShellState,
+) -> anyhow::Result<Expansion> {
+    match value {
+        Some(value) => Ok(value),
+        None if state.options.nounset => Err(FatalError::Unbound(name.to_string()).into()),
+        None => Ok(Expansion::Value(String::new())),
+    }
+}
+
+/// 查找参数的展开结果，$@ 和 ${a[@]} 为多个字段，未设置时为 None
+fn lookup_expansion(name: &str, state: &ShellState) -> Option<Expansion> {
+    if name == "@" {
+        return Some(Expansion::Fields(state.positional.clone()));
+    }
+    if let Some((array, index)) = name.strip_suffix(']').and_then(|n| n.split_once('[')) {
+        // ${NAME[i]}、${NAME[@]}、${NAME[*]}
+        let values = lookup_array(array, state).unwrap_or_default();
+        return match index {
+            "@" => Some(Expansion::Fields(values)),
+            "*" => Some(Expansion::Value(join_with_ifs(&values, state))),
+            _ => index
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| values.get(i).cloned())
+                .map(Expansion::Value),
+        };
+    }
+    lookup_parameter(name, state).map(Expansion::Value)
+}
+
+/// ${name:offset:length} 中的整数
+fn parse_integer(text: &str) -> anyhow::Result<i64> {
+    text.trim()
+        .parse()
+        .map_err(|_| anyhow::anyhow!("{}: integer expression expected", text.trim()))
+}
+
+/// 对每个值分别应用 f
+fn map_values(value: Expansion, mut f: impl FnMut(&str) -> String) -> Expansion {
+    match value {
+        Expansion::Value(value) => Expansion::Value(f(&value)),
+        Expansion::Fields(values) => {
+            Expansion::Fields(values.iter().map(|value| f(value)).collect())
+        }
+    }
+}
+
+/// ${name:offset:length}：负的 offset 从末尾算起，负的 length 表示末尾去掉的字符数；
+/// 对 $@、$* 和 ${a[@]} 则截取其中的若干个值，$@ 和 $* 的下标 0 是 $0
+fn substring_expansion(
+    value: Expansion,
+    name: &str,
+    offset: i64,
+    length: Option<i64>,
+    state: &ShellState,
+) -> anyhow::Result<Expansion> {
+    let slice = |len: usize| -> anyhow::Result<std::ops::Range<usize>> {
+        let len = len as i64;
+        let start = if offset < 0 { len + offset } else { offset };
+        if start < 0 || start > len {
+            return Ok(0..0);
+        }
+        let end = match length {
+            Some(length) if length < 0 => len + length,
+            Some(length) => start.saturating_add(length).min(len),
+            None => len,
+        };
+        if end < start {
+            anyhow::bail!("{}: substring expression < 0", length.unwrap_or_default());
+        }
+        Ok(start as usize..end as usize)
+    };
+    if name == "@" || name == "*" {
+        let values: Vec<String> = std::iter::once(state.shell_name.clone())
+            .chain(state.positional.iter().cloned())
+            .collect();
+        let values = values[slice(values.len())?].to_vec();
+        return Ok(match name {
+            "@" => Expansion::Fields(values),
+            _ => Expansion::Value(join_with_ifs(&values, state)),
+        });
+    }
+    match value {
+        Expansion::Value(value) => {
+            let chars: Vec<char> = value.chars().collect();
+            Ok(Expansion::Value(
+                chars[slice(chars.len())?].iter().collect(),
+            ))
+        }
+        Expansion::Fields(values) => Ok(Expansion::Fields(values[slice(values.len())?].to_vec())),
+    }
+}
+
+/// 与模式匹配的最短或最长前缀的结束位置
+fn prefix_end(value: &str, pattern: &Pattern, longest: bool) -> Option<usize> {
+    let mut ends: Vec<usize> = char_boundaries(value).collect();
+    if longest {
+        ends.reverse();
+    }
+    ends.into_iter().find(|&end| pattern.matches(&value[..end]))
+}
+
+/// 与模式匹配的最短或最长后缀的起始位置
+fn suffix_start(value: &str, pattern: &Pattern, longest: bool) -> Option<usize> {
+    let mut starts: Vec<usize> = char_boundaries(value).collect();
+    if !longest {
+        starts.reverse();
+    }
+    starts
+        .into_iter()
+        .find(|&start| pattern.matches(&value[start..]))
+}
+
+/// 把与模式匹配的子串替换为 replacement：/ 和 // 替换最左最长的匹配（空的匹配不替换），
+/// /# 和 /% 只替换开头或末尾的最长匹配
+fn replace(value: &str, pattern: &Pattern, replacement: &str, mode: ReplaceMode) -> String {
+    match mode {
+        ReplaceMode::Prefix => {
+            return match prefix_end(value, pattern, true) {
+                Some(end) => format!("{}{}", replacement, &value[end..]),
+                None => value.to_string(),
+            };
+        }
+        ReplaceMode::Suffix => {
+            return match suffix_start(value, pattern, true) {
+                Some(start) => format!("{}{}", &value[..start], replacement),
+                None => value.to_string(),
+            };
+        }
+        ReplaceMode::First | ReplaceMode::All => {}
+    }
+    let mut result = String::new();
+    let mut rest = value;
+    while let Some(ch) = rest.chars().next() {
+        let end = char_boundaries(rest)
+            .skip(1)
+            .filter(|&end| pattern.matches(&rest[..end]))
+            .last();
+        match end {
+            Some(end) => {
+                result.push_str(replacement);
+                rest = &rest[end..];
+                if mode == ReplaceMode::First {
+                    break;
+                }
+            }
+            None => {
+                result.push(ch);
+                rest = &rest[ch.len_utf8()..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// 字符串中所有字符边界的字节偏移，包括开头和末尾
+fn char_boundaries(value: &str) -> impl Iterator<Item = usize> + '_ {
+    value
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(value.len()))
+}
+
+/// ${name^^}、${name,,} 转换所有字符，${name^}、${name,} 只转换第一个字符
+fn change_case<I: Iterator<Item = char>>(value: &str, all: bool, convert: fn(char) -> I) -> String {
+    let mut chars = value.chars();
+    if all {
+        return chars.flat_map(convert).collect();
+    }
+    match chars.next() {
+        Some(first) => convert(first).chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// 查找参数值，包括特殊参数和位置参数
+pub fn lookup_parameter(name: &str, state: &ShellState) -> Option<String> {
+    match name {
+        "?" => Some(state.last_status.to_string()),
+        "$" => Some(state.pid.to_string()),
+        "!" => state.last_bg_pid.map(|pid| pid.to_string()),
+        "0" => Some(state.shell_name.clone()),
+        "#" => Some(state.positional.len().to_string()),
+        "-" => Some(state.options.flags()),
+        "@" => Some(state.positional.join(" ")),
+        "*" => Some(join_with_ifs(&state.positional, state)),
+        _ => match name.parse::<usize>() {
+            // ${00} 等同于 $0
+            Ok(0) => Some(state.shell_name.clone()),
+            Ok(n) => state.positional.get(n - 1).cloned(),
+            // 数组名单独使用时取第一个元素
+            Err(_) => match lookup_array(name, state) {
+                Some(values) => values.into_iter().next(),
+                None => state.vars.get(name).map(str::to_string),
+            },
+        },
+    }
+}
+
+/// $* 和 ${a[*]} 以 IFS 的第一个字符连接：IFS 未设置时为空格，为空时直接相连
+fn join_with_ifs(values: &[String], state: &ShellState) -> String {
+    let separator = match state.vars.get("IFS") {
+        Some(ifs) => ifs.chars().next().map(String::from).unwrap_or_default(),
+        None => " ".to_string(),
+    };
+    values.join(&separator)
+}
+
+/// 查找数组变量，目前只有 PIPESTATUS
+fn lookup_array(name: &str, state: &ShellState) -> Option<Vec<String>> {
+    match name {
+        "PIPESTATUS" => Some(state.pipestatus.iter().map(i32::to_string).collect()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 按 IFS 分词后的字段文本
+    fn split(value: &str, ifs: &str) -> Vec<String> {
+        let mut fields = Fields::default();
+        fields.push_split(value, ifs);
+        fields
+            .into_fields()
+            .into_iter()
+            .map(|field| field.into_iter().map(|(ch, _)| ch).collect())
+            .collect()
+    }
+
+    #[test]
+    fn whitespace_ifs_collapses() {
+        assert_eq!(split("  a \t b\n", DEFAULT_IFS), ["a", "b"]);
+        assert!(split("   ", DEFAULT_IFS).is_empty());
+    }
+
+    #[test]
+    fn non_whitespace_ifs_keeps_empty_fields() {
+        assert_eq!(split("a::b", ":"), ["a", "", "b"]);
+        assert_eq!(split(":a", ":"), ["", "a"]);
+        // 末尾的分隔符不产生空字段
+        assert_eq!(split("a:", ":"), ["a"]);
+        assert_eq!(split("a::", ":"), ["a", ""]);
+    }
+
+    #[test]
+    fn mixed_ifs() {
+        // 空白与相邻的非空白分隔符一起只分隔一次
+        assert_eq!(split("a : b", " :"), ["a", "b"]);
+        assert_eq!(split("a :: b", " :"), ["a", "", "b"]);
+        assert_eq!(split(" :a", " :"), ["", "a"]);
+    }
+
+    #[test]
+    fn empty_ifs_does_not_split() {
+        assert_eq!(split(" a b ", ""), [" a b "]);
+    }
+
+    /// 在给定变量下展开一个单词（不分词）
+    fn expand(source: &str, state: &mut ShellState) -> anyhow::Result<String> {
+        let tokens = crate::lexer::tokenize_line(source)?;
+        let crate::lexer::RawToken::Word(word) = &tokens[0].raw else {
+            anyhow::bail!("not a word: {}", source);
+        };
+        let mut rl = rustyline::Editor::new()?;
+        let mut context = ExecutionContext::new(&mut rl, state);
+        expand_word(word, &mut context)
+    }
+
+    fn state_with(vars: &[(&str, &str)]) -> ShellState {
+        let mut state = ShellState::new();
+        for (name, value) in vars {
+            state.vars.set(name, value.to_string());
+        }
+        state
+    }
+
+    #[test]
+    fn conditional_operators() {
+        let mut state = state_with(&[("empty", "")]);
+        state.vars.unset("unset_var");
+        assert_eq!(expand("${unset_var-d}", &mut state).unwrap(), "d");
+        assert_eq!(expand("${empty-d}", &mut state).unwrap(), "");
+        assert_eq!(expand("${empty:-d}", &mut state).unwrap(), "d");
+        assert_eq!(expand("${empty:+alt}", &mut state).unwrap(), "");
+        assert_eq!(expand("${empty+alt}", &mut state).unwrap(), "alt");
+        assert_eq!(expand("${unset_var:=v}", &mut state).unwrap(), "v");
+        assert_eq!(state.vars.get("unset_var"), Some("v"));
+    }
+
+    #[test]
+    fn error_operator_fails() {
+        let mut state = state_with(&[("empty", "")]);
+        let err = expand("${empty:?is empty}", &mut state).unwrap_err();
+        let fatal = err.downcast_ref::<FatalError>().unwrap();
+        assert_eq!(fatal.to_string(), "empty: is empty");
+        assert_eq!(fatal.exit_code(), 127);
+        // 没有冒号时只检查是否设置
+        assert_eq!(expand("${empty?msg}", &mut state).unwrap(), "");
+    }
+
+    #[test]
+    fn length_and_substring() {
+        let mut state = state_with(&[("s", "héllo")]);
+        assert_eq!(expand("${#s}", &mut state).unwrap(), "5");
+        assert_eq!(expand("${s:1:3}", &mut state).unwrap(), "éll");
+        assert_eq!(expand("${s: -2}", &mut state).unwrap(), "lo");
+        assert_eq!(expand("${s:1:-1}", &mut state).unwrap(), "éll");
+    }
+
+    #[test]
+    fn pattern_removal_and_replacement() {
+        let mut state = state_with(&[("path", "/usr/local/lib.tar.gz")]);
+        assert_eq!(
+            expand("${path#*/}", &mut state).unwrap(),
+            "usr/local/lib.tar.gz"
+        );
+        assert_eq!(expand("${path##*/}", &mut state).unwrap(), "lib.tar.gz");
+        assert_eq!(
+            expand("${path%.*}", &mut state).unwrap(),
+            "/usr/local/lib.tar"
+        );
+        assert_eq!(expand("${path%%.*}", &mut state).unwrap(), "/usr/local/lib");
+        assert_eq!(
+            expand("${path/l/L}", &mut state).unwrap(),
+            "/usr/Local/lib.tar.gz"
+        );
+        assert_eq!(
+            expand("${path//l/L}", &mut state).unwrap(),
+            "/usr/LocaL/Lib.tar.gz"
+        );
+        assert_eq!(
+            expand("${path/#\\//x}", &mut state).unwrap(),
+            "xusr/local/lib.tar.gz"
+        );
+        assert_eq!(
+            expand("${path/%gz/xz}", &mut state).unwrap(),
+            "/usr/local/lib.tar.xz"
+        );
+    }
+
+    #[test]
+    fn case_and_indirection() {
+        let mut state = state_with(&[("word", "hello world"), ("upper", "ABC"), ("ref", "word")]);
+        assert_eq!(expand("${word^}", &mut state).unwrap(), "Hello world");
+        assert_eq!(expand("${word^^}", &mut state).unwrap(), "HELLO WORLD");
+        assert_eq!(expand("${upper,,}", &mut state).unwrap(), "abc");
+        assert_eq!(expand("${!ref}", &mut state).unwrap(), "hello world");
+    }
+}
@@ -0,0 +1,140 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, Write},
+    mem::ManuallyDrop,
+    os::unix::io::{AsRawFd, FromRawFd, RawFd},
+};
+
+/// shell 内部持有的 fd 不低于此值，0-9 留给用户重定向
+const MIN_SHELL_FD: RawFd = 10;
+/// 复制到子进程前临时存放来源 fd 的起始编号，避免 dup2 时相互覆盖
+const STAGING_FD: RawFd = 512;
+
+/// 命令的文件描述符表：fd 编号 -> 打开的文件，None 表示该 fd 已被关闭。
+/// 表中没有的 fd 沿用 shell 进程自身的 fd
+#[derive(Debug, Default)]
+pub struct FdTable {
+    fds: BTreeMap<RawFd, Option<File>>,
+}
+
+impl FdTable {
+    pub fn try_clone(&self) -> io::Result<Self> {
+        let mut fds = BTreeMap::new();
+        for (fd, file) in &self.fds {
+            let file = file.as_ref().map(|f| dup_high(f.as_raw_fd())).transpose()?;
+            fds.insert(*fd, file);
+        }
+        Ok(Self { fds })
+    }
+
+    /// fd 当前对应的真实 fd，未打开时返回 None
+    pub fn raw(&self, fd: RawFd) -> Option<RawFd> {
+        match self.fds.get(&fd) {
+            Some(file) => file.as_ref().map(File::as_raw_fd),
+            None => (unsafe { libc::fcntl(fd, libc::F_GETFD) } != -1).then_some(fd),
+        }
+    }
+
+    /// 向 fd 写入全部数据，供内置命令输出使用
+    pub fn write_all(&self, fd: RawFd, buf: &[u8]) -> io::Result<()> {
+        let raw = self
+            .raw(fd)
+            .ok_or_else(|| io::Error::from_raw_os_error(libc::EBADF))?;
+        // 借用 fd，不获取所有权
+        let mut file = ManuallyDrop::new(unsafe { File::from_raw_fd(raw) });
+        file.write_all(buf)
+    }
+
+    /// 文件被移到高位 fd，以免 exec 安装 fd 时与表中的文件冲突
+    pub fn set(&mut self, fd: RawFd, file: File) {
+        let file = if file.as_raw_fd() < MIN_SHELL_FD {
+            dup_high(file.as_raw_fd()).unwrap_or(file)
+        } else {
+            file
+        };
+        self.fds.insert(fd, Some(file));
+    }
+
+    /// n>&- / n<&-
+    pub fn close(&mut self, fd: RawFd) {
+        self.fds.insert(fd, None);
+    }
+
+    /// n>&m / n<&m：fd 成为 source 的副本
+    pub fn dup(&mut self, fd: RawFd, source: RawFd) -> io::Result<()> {
+        let source = self
+            .raw(source)
+            .ok_or_else(|| io::Error::from_raw_os_error(libc::EBADF))?;
+        let file = dup_high(source)?;
+        self.fds.insert(fd, Some(file));
+        Ok(())
+    }
+
+    /// 表项对应的真实 fd，供 install_fds 使用
+    pub fn raw_fds(&self) -> Vec<(RawFd, Option<RawFd>)> {
+        self.fds
+            .iter()
+            .map(|(fd, file)| (*fd, file.as_ref().map(File::as_raw_fd)))
+            .collect()
+    }
+}
+
+/// 把 fd 复制到 shell 内部使用的高位编号
+fn dup_high(fd: RawFd) -> io::Result<File> {
+    let dup = unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, MIN_SHELL_FD) };
+    if dup == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { File::from_raw_fd(dup) })
+}
+
+/// 按 fd 表设置当前进程的 fd：先把来源复制到高位，再 dup2 到目标位置。
+/// 只使用 async-signal-safe 的系统调用且不分配内存，可在 pre_exec 中调用
+pub fn install_fds(fds: &[(RawFd, Option<RawFd>)]) -> io::Result<()> {
+    for (i, (_, raw)) in fds.iter().enumerate() {
+        if let Some(raw) = raw
+            && unsafe { libc::dup2(*raw, STAGING_FD + i as RawFd) } == -1
+        {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    for (i, (fd, raw)) in fds.iter().enumerate() {
+        let staged = STAGING_FD + i as RawFd;
+        unsafe {
+            match raw {
+                Some(_) => {
+                    if libc::dup2(staged, *fd) == -1 {
+                        return Err(io::Error::last_os_error());
+                    }
+                    libc::close(staged);
+                }
+                None => {
+                    libc::close(*fd);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 执行 f 期间占住 3-9 号 fd，使其中打开的长期 fd（如行编辑器持有的终端）落在高位
+pub fn with_user_fds_reserved<T>(f: impl FnOnce() -> T) -> T {
+    let mut placeholders = Vec::new();
+    while let Ok(file) = File::open("/dev/null") {
+        if file.as_raw_fd() >= MIN_SHELL_FD {
+            break;
+        }
+        placeholders.push(file);
+    }
+    f()
+}
+
+/// 创建带 CLOEXEC 标志的管道，避免写端泄漏到无关的子进程
+pub fn pipe() -> io::Result<(File, File)> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) })
+}
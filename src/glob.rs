@@ -0,0 +1,376 @@
+use std::path::Path;
+
+/// 通配模式中的单个元素
+#[derive(Debug, Clone, PartialEq)]
+enum PatternToken {
+    Char(char),
+    AnyChar, // ?
+    Star,    // *
+    Class {
+        negated: bool,
+        items: Vec<ClassItem>,
+    }, // [...]
+}
+
+/// 方括号表达式中的一项
+#[derive(Debug, Clone, PartialEq)]
+enum ClassItem {
+    Char(char),
+    Range(char, char),
+    Named(String), // [:alpha:] 等字符类
+}
+
+impl ClassItem {
+    fn matches(&self, ch: char) -> bool {
+        match self {
+            ClassItem::Char(c) => *c == ch,
+            ClassItem::Range(start, end) => (*start..=*end).contains(&ch),
+            ClassItem::Named(name) => match name.as_str() {
+                "alpha" => ch.is_alphabetic(),
+                "digit" => ch.is_ascii_digit(),
+                "alnum" => ch.is_alphanumeric(),
+                "upper" => ch.is_uppercase(),
+                "lower" => ch.is_lowercase(),
+                "space" => ch.is_whitespace(),
+                "blank" => ch == ' ' || ch == '\t',
+                "punct" => ch.is_ascii_punctuation(),
+                "xdigit" => ch.is_ascii_hexdigit(),
+                "cntrl" => ch.is_control(),
+                _ => false,
+            },
+        }
+    }
+}
+
+impl PatternToken {
+    fn matches(&self, ch: char) -> bool {
+        match self {
+            PatternToken::Char(c) => *c == ch,
+            PatternToken::AnyChar => true,
+            PatternToken::Star => false,
+            PatternToken::Class { negated, items } => {
+                items.iter().any(|item| item.matches(ch)) != *negated
+            }
+        }
+    }
+}
+
+/// shell 通配模式，用于路径名展开、case 和参数展开中的模式匹配
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    tokens: Vec<PatternToken>,
+}
+
+impl Pattern {
+    /// 由 (字符, 是否被引用) 序列构造，被引用的字符按字面匹配
+    pub fn new(chars: &[(char, bool)]) -> Self {
+        let mut tokens = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let (ch, quoted) = chars[i];
+            i += 1;
+            if quoted {
+                tokens.push(PatternToken::Char(ch));
+                continue;
+            }
+            match ch {
+                '*' => {
+                    // 连续的 * 等价于一个
+                    if tokens.last() != Some(&PatternToken::Star) {
+                        tokens.push(PatternToken::Star);
+                    }
+                }
+                '?' => tokens.push(PatternToken::AnyChar),
+                '[' => match parse_class(&chars[i..]) {
+                    Some((token, len)) => {
+                        tokens.push(token);
+                        i += len;
+                    }
+                    // 没有闭合的 ]，按字面处理
+                    None => tokens.push(PatternToken::Char('[')),
+                },
+                '\\' if i < chars.len() => {
+                    tokens.push(PatternToken::Char(chars[i].0));
+                    i += 1;
+                }
+                _ => tokens.push(PatternToken::Char(ch)),
+            }
+        }
+        Self { tokens }
+    }
+
+    /// 是否不含任何通配符
+    pub fn is_literal(&self) -> bool {
+        self.tokens
+            .iter()
+            .all(|token| matches!(token, PatternToken::Char(_)))
+    }
+
+    /// 模式是否以字面的 . 开头（此时才匹配隐藏文件）
+    fn starts_with_dot(&self) -> bool {
+        self.tokens.first() == Some(&PatternToken::Char('.'))
+    }
+
+    /// 模式的字面文本，仅在 is_literal 时有意义
+    fn literal_text(&self) -> String {
+        self.tokens
+            .iter()
+            .filter_map(|token| match token {
+                PatternToken::Char(c) => Some(*c),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// 整个文本是否与模式匹配
+    pub fn matches(&self, text: &str) -> bool {
+        let text: Vec<char> = text.chars().collect();
+        let (mut t, mut s) = (0, 0);
+        // 最近一个 * 的位置以及它当前吞掉的文本终点，用于回溯
+        let mut star: Option<(usize, usize)> = None;
+        while s < text.len() {
+            match self.tokens.get(t) {
+                Some(PatternToken::Star) => {
+                    star = Some((t, s));
+                    t += 1;
+                    continue;
+                }
+                Some(token) if token.matches(text[s]) => {
+                    t += 1;
+                    s += 1;
+                    continue;
+                }
+                _ => {}
+            }
+            match star {
+                Some((star_t, star_s)) => {
+                    t = star_t + 1;
+                    s = star_s + 1;
+                    star = Some((star_t, star_s + 1));
+                }
+                None => return false,
+            }
+        }
+        self.tokens[t..]
+            .iter()
+            .all(|token| *token == PatternToken::Star)
+    }
+}
+
+/// 解析 [ 之后的方括号表达式，返回记号和消耗的字符数
+fn parse_class(chars: &[(char, bool)]) -> Option<(PatternToken, usize)> {
+    let mut i = 0;
+    let negated = matches!(chars.first(), Some(('!' | '^', false)));
+    if negated {
+        i += 1;
+    }
+    let mut items = Vec::new();
+    let start = i;
+    while i < chars.len() {
+        let (ch, quoted) = chars[i];
+        // 首个 ] 按字面处理
+        if ch == ']' && !quoted && i > start {
+            return Some((PatternToken::Class { negated, items }, i + 1));
+        }
+        if ch == '[' && !quoted && chars.get(i + 1) == Some(&(':', false)) {
+            let rest: String = chars[i + 2..].iter().map(|(c, _)| *c).collect();
+            if let Some(end) = rest.find(":]") {
+                items.push(ClassItem::Named(rest[..end].to_string()));
+                i += 2 + rest[..end].chars().count() + 2;
+                continue;
+            }
+        }
+        match (chars.get(i + 1), chars.get(i + 2)) {
+            (Some(('-', false)), Some((end, _))) if *end != ']' => {
+                items.push(ClassItem::Range(ch, *end));
+                i += 3;
+            }
+            _ => {
+                items.push(ClassItem::Char(ch));
+                i += 1;
+            }
+        }
+    }
+    None
+}
+
+/// 路径名展开使用的选项
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GlobOptions {
+    pub dotglob: bool,  // * 也匹配隐藏文件
+    pub globstar: bool, // ** 递归匹配任意层目录
+}
+
+/// 对带引用信息的单词做路径名展开，返回排序后的匹配结果
+pub fn glob(chars: &[(char, bool)], options: GlobOptions) -> Vec<String> {
+    let absolute = chars.first().map(|(c, _)| *c) == Some('/');
+    let components: Vec<&[(char, bool)]> = chars
+        .split(|(c, _)| *c == '/')
+        .skip(usize::from(absolute))
+        .collect();
+
+    let mut paths = vec![if absolute {
+        "/".to_string()
+    } else {
+        String::new()
+    }];
+    for (i, component) in components.iter().enumerate() {
+        let is_last = i == components.len() - 1;
+        let pattern = Pattern::new(component);
+        let mut next = Vec::new();
+        for base in &paths {
+            if component.is_empty() {
+                // 连续或结尾的 /，结尾时只保留目录
+                if !is_last || is_dir(base) {
+                    next.push(base.clone());
+                }
+            } else if options.globstar && *component == [('*', false), ('*', false)] {
+                // ** 匹配零层或多层目录，位于结尾时也匹配文件
+                if !is_last || !base.is_empty() {
+                    next.push(base.clone());
+                }
+                collect_tree(base, options, is_last, &mut next);
+            } else if pattern.is_literal() {
+                next.push(join(base, &pattern.literal_text()));
+            } else {
+                for name in read_dir_names(base) {
+                    let hidden = name.starts_with('.');
+                    if hidden && !options.dotglob && !pattern.starts_with_dot() {
+                        continue;
+                    }
+                    if pattern.matches(&name) {
+                        next.push(join(base, &name));
+                    }
+                }
+            }
+        }
+        // 中间层级只保留目录
+        if !is_last {
+            next.retain(|path| path.is_empty() || is_dir(path));
+            paths = next.into_iter().map(|path| with_slash(&path)).collect();
+        } else {
+            paths = next;
+        }
+    }
+
+    let mut matches: Vec<String> = paths
+        .into_iter()
+        .filter(|path| !path.is_empty() && Path::new(path).symlink_metadata().is_ok())
+        .collect();
+    matches.sort();
+    matches.dedup();
+    matches
+}
+
+/// globstar：递归收集 base 下的所有目录，include_files 时也收集文件
+fn collect_tree(base: &str, options: GlobOptions, include_files: bool, out: &mut Vec<String>) {
+    for name in read_dir_names(base) {
+        if name.starts_with('.') && !options.dotglob {
+            continue;
+        }
+        let path = join(base, &name);
+        // 不跟随符号链接，避免循环
+        let is_dir = Path::new(&path)
+            .symlink_metadata()
+            .is_ok_and(|meta| meta.is_dir());
+        if is_dir || include_files {
+            out.push(path.clone());
+        }
+        if is_dir {
+            collect_tree(&path, options, include_files, out);
+        }
+    }
+}
+
+fn read_dir_names(base: &str) -> Vec<String> {
+    let dir = if base.is_empty() { "." } else { base };
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn join(base: &str, name: &str) -> String {
+    format!("{}{}", with_slash(base), name)
+}
+
+fn with_slash(path: &str) -> String {
+    if path.is_empty() || path.ends_with('/') {
+        path.to_string()
+    } else {
+        format!("{}/", path)
+    }
+}
+
+fn is_dir(path: &str) -> bool {
+    Path::new(if path.is_empty() { "." } else { path }).is_dir()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 未加引号的模式
+    fn pattern(text: &str) -> Pattern {
+        Pattern::new(&unquoted(text))
+    }
+
+    fn unquoted(text: &str) -> Vec<(char, bool)> {
+        text.chars().map(|ch| (ch, false)).collect()
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(pattern("*.rs").matches("main.rs"));
+        assert!(!pattern("*.rs").matches("main.rsx"));
+        assert!(pattern("a?c").matches("abc"));
+        assert!(pattern("[a-c]x").matches("bx"));
+        assert!(!pattern("[!a-c]x").matches("bx"));
+        assert!(pattern("*a*b").matches("xxaxxb"));
+    }
+
+    #[test]
+    fn quoted_chars_are_literal() {
+        let chars = [('*', true), ('.', false), ('r', false), ('s', false)];
+        let literal = Pattern::new(&chars);
+        assert!(literal.is_literal());
+        assert!(literal.matches("*.rs"));
+        assert!(!literal.matches("main.rs"));
+        // 没有闭合的 [ 按字面处理
+        assert!(pattern("[ab").matches("[ab"));
+    }
+
+    #[test]
+    fn glob_directory() {
+        let dir = std::env::temp_dir().join(format!("glob-test-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        for name in ["a.rs", "b.rs", "c.txt", ".hidden.rs", "sub/d.rs"] {
+            std::fs::write(dir.join(name), "").unwrap();
+        }
+        let base = dir.to_str().unwrap();
+        let options = GlobOptions::default();
+        assert_eq!(
+            glob(&unquoted(&format!("{}/*.rs", base)), options),
+            [format!("{}/a.rs", base), format!("{}/b.rs", base)]
+        );
+        let options = GlobOptions {
+            dotglob: true,
+            ..Default::default()
+        };
+        assert_eq!(glob(&unquoted(&format!("{}/*.rs", base)), options).len(), 3);
+        let options = GlobOptions {
+            globstar: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            glob(&unquoted(&format!("{}/**/d.rs", base)), options),
+            [format!("{}/sub/d.rs", base)]
+        );
+        assert!(glob(&unquoted(&format!("{}/*.none", base)), options).is_empty());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,567 @@
+use std::{
+    io,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use libc::pid_t;
+
+/// 交互式 shell 自身忽略、子进程需要恢复默认处理的信号
+const SHELL_IGNORED_SIGNALS: [libc::c_int; 5] = [
+    libc::SIGINT,
+    libc::SIGQUIT,
+    libc::SIGTSTP,
+    libc::SIGTTIN,
+    libc::SIGTTOU,
+];
+
+/// wait 等待被 Ctrl-C 中断的标志
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// 作业状态
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobState {
+    Running,
+    Stopped,
+    Done(i32), // 最后一个进程的退出状态
+}
+
+/// wait 等待作业的结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WaitOutcome {
+    Done(i32),   // 作业结束，最后一个进程的退出状态
+    Stopped,     // 作业已停止，不再等待
+    Interrupted, // 等待被 Ctrl-C 中断
+}
+
+/// 作业中的一个进程
+#[derive(Debug, Clone)]
+pub struct JobProcess {
+    pub pid: pid_t,
+    pub status: Option<i32>, // 结束后的退出状态
+    pub stopped: bool,
+}
+
+/// 一个作业：同一进程组中的管道
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub id: usize,
+    pub pgid: pid_t,
+    pub processes: Vec<JobProcess>,
+    pub command: String, // 命令文本，不含后台运行的 &
+    pub notified: bool,  // 状态变化是否已报告
+}
+
+impl Job {
+    pub fn state(&self) -> JobState {
+        if self.processes.iter().all(|p| p.status.is_some()) {
+            JobState::Done(self.processes.last().and_then(|p| p.status).unwrap_or(0))
+        } else if self
+            .processes
+            .iter()
+            .any(|p| p.status.is_none() && p.stopped)
+        {
+            JobState::Stopped
+        } else {
+            JobState::Running
+        }
+    }
+
+    /// jobs 输出中的状态文本
+    pub fn state_text(&self) -> String {
+        match self.state() {
+            JobState::Running => "Running".to_string(),
+            JobState::Stopped => "Stopped".to_string(),
+            JobState::Done(0) => "Done".to_string(),
+            JobState::Done(code) => format!("Exit {}", code),
+        }
+    }
+
+    /// 显示的命令文本，在后台运行的作业加上 &
+    pub fn display_command(&self) -> String {
+        match self.state() {
+            JobState::Running => format!("{} &", self.command),
+            _ => self.command.clone(),
+        }
+    }
+
+    /// 非阻塞地收集进程状态变化
+    fn poll(&mut self) {
+        for process in self.processes.iter_mut().filter(|p| p.status.is_none()) {
+            let flags = libc::WNOHANG | libc::WUNTRACED | libc::WCONTINUED;
+            if let Some(status) = wait_pid(process.pid, flags)
+                && apply_status(process, status)
+            {
+                self.notified = false;
+            }
+        }
+    }
+}
+
+/// 作业表，同时负责前台进程组与终端的交接
+#[derive(Debug, Default)]
+pub struct JobTable {
+    jobs: Vec<Job>,
+    job_control: bool,         // 交互式 shell 且拥有终端时启用
+    shell_pgid: pid_t,         // shell 自身的进程组
+    spawn_pgid: Option<pid_t>, // 正在启动的前台作业的进程组
+    current: Option<usize>,    // %+
+    previous: Option<usize>,   // %-
+}
+
+impl JobTable {
+    /// 交互式 shell 启动时接管终端：进入独立进程组，忽略 Ctrl-C 等信号，
+    /// 让它们只作用于前台作业
+    pub fn init_interactive(&mut self) {
+        unsafe {
+            if libc::isatty(0) == 0 {
+                return;
+            }
+            // 在后台启动时等待被放到前台
+            loop {
+                let pgrp = libc::getpgrp();
+                if libc::tcgetpgrp(0) == pgrp {
+                    break;
+                }
+                libc::kill(-pgrp, libc::SIGTTIN);
+            }
+            for signal in SHELL_IGNORED_SIGNALS {
+                libc::signal(signal, libc::SIG_IGN);
+            }
+            let pid = libc::getpid();
+            libc::setpgid(0, pid);
+            libc::tcsetpgrp(0, pid);
+            self.shell_pgid = pid;
+        }
+        self.job_control = true;
+    }
+
+    /// fork 出的子 shell 恢复信号的默认处理，不再进行作业控制，也不管理父 shell 的作业
+    pub fn enter_subshell(&mut self) {
+        if self.job_control {
+            reset_signals();
+        }
+        // Rust 运行时忽略了 SIGPIPE，子 shell 的读端关闭后写入应终止子 shell
+        unsafe {
+            libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+        }
+        *self = Self::default();
+    }
+
+    pub fn job_control(&self) -> bool {
+        self.job_control
+    }
+
+    /// 新子进程应加入的进程组，0 表示新建；未启用作业控制时为 None
+    pub fn child_pgid(&self) -> Option<pid_t> {
+        self.job_control.then(|| self.spawn_pgid.unwrap_or(0))
+    }
+
+    /// 记录刚启动的前台子进程，第一个进程成为进程组长并获得终端
+    pub fn register_child(&mut self, pid: pid_t) {
+        if !self.job_control {
+            return;
+        }
+        let pgid = *self.spawn_pgid.get_or_insert(pid);
+        unsafe {
+            // 与子进程中的 setpgid 重复调用，避免竞争
+            libc::setpgid(pid, pgid);
+            libc::tcsetpgrp(0, pgid);
+        }
+    }
+
+    /// 添加一个作业并设为当前作业，返回作业号
+    pub fn add(&mut self, pgid: pid_t, pids: &[pid_t], command: String) -> usize {
+        let id = self.jobs.last().map_or(1, |job| job.id + 1);
+        self.jobs.push(Job {
+            id,
+            pgid,
+            processes: pids
+                .iter()
+                .map(|&pid| JobProcess {
+                    pid,
+                    status: None,
+                    stopped: false,
+                })
+                .collect(),
+            command,
+            notified: true,
+        });
+        self.set_current(id);
+        id
+    }
+
+    fn set_current(&mut self, id: usize) {
+        if self.current != Some(id) {
+            self.previous = self.current;
+            self.current = Some(id);
+        }
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Job> {
+        self.jobs.iter().find(|job| job.id == id)
+    }
+
+    pub fn remove(&mut self, id: usize) -> Option<Job> {
+        let index = self.jobs.iter().position(|job| job.id == id)?;
+        let job = self.jobs.remove(index);
+        if self.current == Some(id) {
+            self.current = self.previous.take();
+        }
+        if self.previous == Some(id) {
+            self.previous = None;
+        }
+        // 空缺的 %+ / %- 由最近的作业补上
+        if self.current.is_none() {
+            self.current = self.jobs.last().map(|job| job.id);
+        }
+        if self.previous.is_none() {
+            let current = self.current;
+            self.previous = self
+                .jobs
+                .iter()
+                .rev()
+                .map(|job| job.id)
+                .find(|id| Some(*id) != current);
+        }
+        Some(job)
+    }
+
+    pub fn ids(&self) -> Vec<usize> {
+        self.jobs.iter().map(|job| job.id).collect()
+    }
+
+    pub fn has_stopped(&mut self) -> bool {
+        self.update();
+        self.jobs.iter().any(|job| job.state() == JobState::Stopped)
+    }
+
+    /// 作业号后的 +/- 标记
+    pub fn marker(&self, id: usize) -> char {
+        if self.current == Some(id) {
+            '+'
+        } else if self.previous == Some(id) {
+            '-'
+        } else {
+            ' '
+        }
+    }
+
+    /// 解析作业说明：%n、%%、%+、%-、%name 或进程号
+    pub fn resolve(&self, spec: Option<&str>) -> Result<usize, String> {
+        let Some(spec) = spec else {
+            return self
+                .current
+                .ok_or_else(|| "current: no such job".to_string());
+        };
+        let found = match spec.strip_prefix('%') {
+            Some("" | "%" | "+") => self.current,
+            Some("-") => self.previous,
+            Some(n) if n.bytes().all(|b| b.is_ascii_digit()) => {
+                n.parse().ok().and_then(|id| self.get(id)).map(|job| job.id)
+            }
+            Some(prefix) => self
+                .jobs
+                .iter()
+                .rev()
+                .find(|job| job.command.starts_with(prefix))
+                .map(|job| job.id),
+            None => spec.parse::<pid_t>().ok().and_then(|pid| {
+                self.jobs
+                    .iter()
+                    .find(|job| job.processes.iter().any(|p| p.pid == pid))
+                    .map(|job| job.id)
+            }),
+        };
+        found.ok_or_else(|| format!("{}: no such job", spec))
+    }
+
+    /// 格式化一行作业信息
+    pub fn format_job(&self, id: usize, long: bool) -> String {
+        let Some(job) = self.get(id) else {
+            return String::new();
+        };
+        // -l 时在状态前显示进程组号
+        let pid = if long {
+            format!(" {} ", job.pgid)
+        } else {
+            "  ".to_string()
+        };
+        format!(
+            "[{}]{}{}{:<24}{}\n",
+            job.id,
+            self.marker(id),
+            pid,
+            job.state_text(),
+            job.display_command()
+        )
+    }
+
+    /// 收集所有作业的状态变化
+    pub fn update(&mut self) {
+        for job in &mut self.jobs {
+            job.poll();
+        }
+    }
+
+    /// 报告后台作业的状态变化并移除已结束的作业，在显示提示符前调用
+    pub fn notify(&mut self) -> String {
+        if !self.job_control {
+            return String::new();
+        }
+        self.update();
+        let mut output = String::new();
+        for id in self.ids() {
+            let Some(job) = self.get(id) else { continue };
+            if job.notified {
+                continue;
+            }
+            output.push_str(&self.format_job(id, false));
+            if matches!(job.state(), JobState::Done(_)) {
+                self.remove(id);
+            } else if let Some(job) = self.jobs.iter_mut().find(|job| job.id == id) {
+                job.notified = true;
+            }
+        }
+        output
+    }
+
+    /// 移除已结束的作业（jobs 列出后调用）
+    pub fn remove_done(&mut self) {
+        for id in self.ids() {
+            if self
+                .get(id)
+                .is_some_and(|job| matches!(job.state(), JobState::Done(_)))
+            {
+                self.remove(id);
+            }
+        }
+    }
+
+    /// 等待前台进程全部结束或停止，返回每个进程的退出状态。
+    /// 有进程被 Ctrl-Z 停止时，剩余进程登记为停止的作业
+    pub fn wait_foreground(&mut self, pids: &[pid_t], command: &str) -> Vec<i32> {
+        let pgid = self.spawn_pgid.take().or_else(|| pids.first().copied());
+        let mut processes: Vec<JobProcess> = pids
+            .iter()
+            .map(|&pid| JobProcess {
+                pid,
+                status: None,
+                stopped: false,
+            })
+            .collect();
+        for process in &mut processes {
+            wait_process(process);
+        }
+        self.reclaim_terminal();
+        // 前台作业被 Ctrl-C 终止时换行，使提示符从新行开始
+        if self.job_control
+            && processes
+                .iter()
+                .any(|p| p.status == Some(128 + libc::SIGINT))
+        {
+            eprintln!();
+        }
+        let statuses = processes
+            .iter()
+            .map(|p| p.status.unwrap_or(128 + libc::SIGTSTP))
+            .collect();
+        if processes.iter().any(|p| p.stopped && p.status.is_none()) {
+            let pids: Vec<pid_t> = processes.iter().map(|p| p.pid).collect();
+            let id = self.add(pgid.unwrap_or_default(), &pids, command.to_string());
+            if let Some(job) = self.jobs.iter_mut().find(|job| job.id == id) {
+                job.processes = processes;
+            }
+            eprint!("\n{}", self.format_job(id, false));
+        }
+        statuses
+    }
+
+    /// fg：把作业放到前台继续运行并等待
+    pub fn foreground(&mut self, id: usize) -> io::Result<i32> {
+        let Some(index) = self.jobs.iter().position(|job| job.id == id) else {
+            return Err(io::Error::from_raw_os_error(libc::ESRCH));
+        };
+        let pgid = self.jobs[index].pgid;
+        unsafe {
+            if self.job_control {
+                libc::tcsetpgrp(0, pgid);
+            }
+            if libc::kill(-pgid, libc::SIGCONT) == -1 {
+                let err = io::Error::last_os_error();
+                self.reclaim_terminal();
+                return Err(err);
+            }
+        }
+        let job = &mut self.jobs[index];
+        for process in &mut job.processes {
+            process.stopped = false;
+        }
+        for process in &mut job.processes {
+            wait_process(process);
+        }
+        self.reclaim_terminal();
+        let job = &mut self.jobs[index];
+        match job.state() {
+            JobState::Done(code) => {
+                self.remove(id);
+                Ok(code)
+            }
+            _ => {
+                job.notified = true;
+                self.set_current(id);
+                eprint!("\n{}", self.format_job(id, false));
+                Ok(128 + libc::SIGTSTP)
+            }
+        }
+    }
+
+    /// bg：让停止的作业在后台继续运行
+    pub fn background(&mut self, id: usize) -> io::Result<()> {
+        let Some(job) = self.jobs.iter_mut().find(|job| job.id == id) else {
+            return Err(io::Error::from_raw_os_error(libc::ESRCH));
+        };
+        if unsafe { libc::kill(-job.pgid, libc::SIGCONT) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        for process in &mut job.processes {
+            process.stopped = false;
+        }
+        Ok(())
+    }
+
+    /// wait：阻塞等待作业结束或停止，交互式 shell 中可以用 Ctrl-C 中断
+    pub fn wait_job(&mut self, id: usize) -> WaitOutcome {
+        let _guard = self.job_control.then(InterruptGuard::install);
+        let Some(job) = self.jobs.iter_mut().find(|job| job.id == id) else {
+            return WaitOutcome::Done(127);
+        };
+        for process in &mut job.processes {
+            while process.status.is_none() && !process.stopped {
+                if INTERRUPTED.load(Ordering::SeqCst) {
+                    // 与前台作业被 Ctrl-C 终止时一样换行
+                    eprintln!();
+                    return WaitOutcome::Interrupted;
+                }
+                let mut status = 0;
+                match unsafe { libc::waitpid(process.pid, &mut status, libc::WUNTRACED) } {
+                    // 被信号中断时回到循环开头检查是否是 Ctrl-C
+                    -1 if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => {}
+                    -1 | 0 => process.status = Some(127),
+                    _ => {
+                        if apply_status(process, status) {
+                            job.notified = false;
+                        }
+                    }
+                }
+            }
+        }
+        match job.state() {
+            JobState::Done(code) => {
+                self.remove(id);
+                WaitOutcome::Done(code)
+            }
+            _ => WaitOutcome::Stopped,
+        }
+    }
+
+    /// 把终端交还给 shell
+    fn reclaim_terminal(&self) {
+        if self.job_control {
+            unsafe {
+                libc::tcsetpgrp(0, self.shell_pgid);
+            }
+        }
+    }
+}
+
+/// wait 期间捕获 SIGINT，使阻塞的 waitpid 返回 EINTR；析构时恢复原来的处理
+struct InterruptGuard {
+    previous: libc::sigaction,
+}
+
+impl InterruptGuard {
+    fn install() -> Self {
+        extern "C" fn record_interrupt(_: libc::c_int) {
+            INTERRUPTED.store(true, Ordering::SeqCst);
+        }
+        INTERRUPTED.store(false, Ordering::SeqCst);
+        unsafe {
+            // 不设置 SA_RESTART，系统调用不会自动重启
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = record_interrupt as extern "C" fn(libc::c_int) as usize;
+            libc::sigemptyset(&mut action.sa_mask);
+            let mut previous: libc::sigaction = std::mem::zeroed();
+            libc::sigaction(libc::SIGINT, &action, &mut previous);
+            Self { previous }
+        }
+    }
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        unsafe {
+            libc::sigaction(libc::SIGINT, &self.previous, std::ptr::null_mut());
+        }
+    }
+}
+
+/// 在子进程 exec 之前调用：加入作业的进程组并恢复信号的默认处理。
+/// 只使用 async-signal-safe 的系统调用
+pub fn prepare_child(pgid: Option<pid_t>) -> io::Result<()> {
+    if let Some(pgid) = pgid {
+        unsafe {
+            libc::setpgid(0, pgid);
+        }
+        reset_signals();
+    }
+    Ok(())
+}
+
+/// 恢复 shell 忽略的信号的默认处理
+pub fn reset_signals() {
+    for signal in SHELL_IGNORED_SIGNALS {
+        unsafe {
+            libc::signal(signal, libc::SIG_DFL);
+        }
+    }
+}
+
+/// 阻塞等待进程结束或停止
+fn wait_process(process: &mut JobProcess) {
+    while process.status.is_none() && !process.stopped {
+        match wait_pid(process.pid, libc::WUNTRACED) {
+            Some(status) => {
+                apply_status(process, status);
+            }
+            // 进程已被回收（例如不是本 shell 的子进程）
+            None => process.status = Some(127),
+        }
+    }
+}
+
+/// waitpid 的封装，EINTR 时重试；无状态变化或出错时返回 None
+fn wait_pid(pid: pid_t, flags: libc::c_int) -> Option<libc::c_int> {
+    let mut status = 0;
+    loop {
+        match unsafe { libc::waitpid(pid, &mut status, flags) } {
+            -1 if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => continue,
+            -1 | 0 => return None,
+            _ => return Some(status),
+        }
+    }
+}
+
+/// 根据 waitpid 的状态更新进程，返回是否需要报告（继续运行不报告）
+fn apply_status(process: &mut JobProcess, status: libc::c_int) -> bool {
+    if libc::WIFSTOPPED(status) {
+        process.stopped = true;
+    } else if libc::WIFCONTINUED(status) {
+        process.stopped = false;
+        return false;
+    } else if libc::WIFSIGNALED(status) {
+        process.status = Some(128 + libc::WTERMSIG(status));
+    } else {
+        process.status = Some(libc::WEXITSTATUS(status));
+    }
+    true
+}
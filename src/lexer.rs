@@ -1,146 +1,1101 @@
+use crate::span::Span;
+
+/// 带源位置的记号
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub raw: RawToken,
+    pub span: Span,
+}
+
 /// 原始词法分析结果
 #[derive(Debug, Clone, PartialEq)]
 pub enum RawToken {
-    Word(String),
-    Pipe,         // |
-    IoNumber(u8), // 0,1,2... 仅在重定向前有意义
+    Word(Word),
+    Pipe,               // |
+    And,                // &&
+    Or,                 // ||
+    Semicolon,          // ;
+    DoubleSemicolon,    // ;; 结束 case 分支
+    SemicolonAnd,       // ;& 继续执行下一个 case 分支
+    DoubleSemicolonAnd, // ;;& 继续测试后面的 case 模式
+    Background,         // &
+    LParen,             // (
+    RParen,             // )
+    Newline,            // 换行，与 ; 一样分隔命令
+    IoNumber(u8),       // 0,1,2... 仅在重定向前有意义
     Redirect(RedirectOp),
+    HeredocBody(Word), // here-document 正文，替换紧跟在 << 之后的分隔符
+}
+
+impl Token {
+    pub fn new(raw: RawToken, span: Span) -> Self {
+        Self { raw, span }
+    }
+}
+
+/// 需要调用方继续提供输入的词法错误
+#[derive(Debug, thiserror::Error)]
+pub enum LexError {
+    #[error("here-document delimited by end-of-file (wanted `{0}')")]
+    UnterminatedHeredoc(String),
+    /// start 为开始引号的位置
+    #[error("unexpected EOF while looking for matching `{kind}'")]
+    UnterminatedQuote { kind: QuoteKind, start: Span },
+    #[error("unexpected EOF after `\\'")]
+    TrailingBackslash,
+}
+
+impl LexError {
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            LexError::UnterminatedQuote { start, .. } => Some(*start),
+            _ => None,
+        }
+    }
+}
+
+/// 未闭合的引号种类，显示为期待的闭合字符
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuoteKind {
+    Single,      // '
+    Double,      // "
+    Backquote,   // `
+    DollarParen, // $(
+    DollarBrace, // ${
+    Arithmetic,  // $((
+}
+
+impl std::fmt::Display for QuoteKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let closing = match self {
+            QuoteKind::Single => '\'',
+            QuoteKind::Double => '"',
+            QuoteKind::Backquote => '`',
+            QuoteKind::DollarParen | QuoteKind::Arithmetic => ')',
+            QuoteKind::DollarBrace => '}',
+        };
+        write!(f, "{}", closing)
+    }
 }
 
 /// 重定向操作符
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum RedirectOp {
-    Out,       // >
-    OutAppend, // >>
-    In,        // <
-    Heredoc,   // <<
-    DupOut,    // >&
-    DupIn,     // <&
+    Out,          // >
+    OutAppend,    // >>
+    In,           // <
+    Heredoc,      // <<
+    HeredocStrip, // <<-
+    DupOut,       // >&
+    DupIn,        // <&
+    ReadWrite,    // <>
+    Clobber,      // >|
+    OutBoth,      // &>
+    AppendBoth,   // &>>
+}
+
+/// 单词片段，保留引用信息，使展开、分词、路径名展开和去引号能按 POSIX 顺序进行
+#[derive(Debug, Clone, PartialEq)]
+pub enum WordPart {
+    Literal(String),             // 未加引号的文本，可以包含通配符
+    SingleQuoted(String),        // '...' 或反斜杠转义的字符：按字面处理
+    DoubleQuoted(Vec<WordPart>), // "..."：其中的文本按字面处理，展开结果不分词
+    Parameter(Parameter),        // $name 或 ${name}
+    CommandSubst(String),        // $(...) 或 `...` 中的命令文本
+    Arithmetic(Vec<WordPart>),   // $((...))：表达式先做参数展开和命令替换再求值
+}
+
+/// 参数展开
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: String,            // 变量名、位置参数或特殊参数，${a[i]} 时包含下标
+    pub braced: bool,            // 是否写作 ${...}
+    pub indirect: bool,          // ${!name}：以 name 的值作为参数名
+    pub op: Option<ParameterOp>, // ${...} 中参数名之后的操作
+}
+
+impl Parameter {
+    fn new(name: String, braced: bool) -> Self {
+        Self {
+            name,
+            braced,
+            indirect: false,
+            op: None,
+        }
+    }
+}
+
+impl std::fmt::Display for Parameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.braced {
+            return write!(f, "${}", self.name);
+        }
+        let prefix = match (&self.op, self.indirect) {
+            (Some(ParameterOp::Length), _) => "#",
+            (_, true) => "!",
+            _ => "",
+        };
+        let op = self
+            .op
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_default();
+        write!(f, "${{{}{}{}}}", prefix, self.name, op)
+    }
 }
 
-/// 词法分析器状态
+/// ${...} 中的操作，colon 表示写作 :- 等形式，此时空值也视为未设置
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParameterOp {
+    // ${#name}
+    Length,
+    // ${name:-word}
+    Default {
+        colon: bool,
+        word: Word,
+    },
+    // ${name:=word}
+    Assign {
+        colon: bool,
+        word: Word,
+    },
+    // ${name:?word}
+    Error {
+        colon: bool,
+        word: Word,
+    },
+    // ${name:+word}
+    Alternative {
+        colon: bool,
+        word: Word,
+    },
+    // ${name#pat} 或 ${name##pat}
+    RemovePrefix {
+        longest: bool,
+        pattern: Word,
+    },
+    // ${name%pat} 或 ${name%%pat}
+    RemoveSuffix {
+        longest: bool,
+        pattern: Word,
+    },
+    // ${name:offset} 或 ${name:offset:length}
+    Substring {
+        offset: Word,
+        length: Option<Word>,
+    },
+    // ${name/pat/rep}、${name//pat/rep}、${name/#pat/rep} 或 ${name/%pat/rep}
+    Replace {
+        mode: ReplaceMode,
+        pattern: Word,
+        replacement: Word,
+    },
+    // ${name^} 或 ${name^^}
+    UpperCase {
+        all: bool,
+    },
+    // ${name,} 或 ${name,,}
+    LowerCase {
+        all: bool,
+    },
+    // 无法识别的 ${...} 的全部文本，展开时报告 bad substitution
+    Invalid(String),
+}
+
+/// ${name/pat/rep} 的替换方式
 #[derive(Debug, Clone, Copy, PartialEq)]
-enum LexerState {
-    Normal,
-    DoubleQuote,
-    SingleQuote,
-    Escaping,
-    DoubleQuoteEscaping,
+pub enum ReplaceMode {
+    First,  // /：第一个匹配
+    All,    // //：所有匹配
+    Prefix, // /#：匹配开头
+    Suffix, // /%：匹配末尾
+}
+
+impl ReplaceMode {
+    fn as_str(self) -> &'static str {
+        match self {
+            ReplaceMode::First => "/",
+            ReplaceMode::All => "//",
+            ReplaceMode::Prefix => "/#",
+            ReplaceMode::Suffix => "/%",
+        }
+    }
+}
+
+impl std::fmt::Display for ParameterOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let colon = |colon: bool| if colon { ":" } else { "" };
+        let twice = |op: &str, all: bool| if all { op.repeat(2) } else { op.to_string() };
+        match self {
+            ParameterOp::Length => Ok(()),
+            ParameterOp::Default { colon: c, word } => write!(f, "{}-{}", colon(*c), word.source()),
+            ParameterOp::Assign { colon: c, word } => write!(f, "{}={}", colon(*c), word.source()),
+            ParameterOp::Error { colon: c, word } => write!(f, "{}?{}", colon(*c), word.source()),
+            ParameterOp::Alternative { colon: c, word } => {
+                write!(f, "{}+{}", colon(*c), word.source())
+            }
+            ParameterOp::RemovePrefix { longest, pattern } => {
+                write!(f, "{}{}", twice("#", *longest), pattern.source())
+            }
+            ParameterOp::RemoveSuffix { longest, pattern } => {
+                write!(f, "{}{}", twice("%", *longest), pattern.source())
+            }
+            ParameterOp::Substring { offset, length } => {
+                write!(f, ":{}", offset.source())?;
+                match length {
+                    Some(length) => write!(f, ":{}", length.source()),
+                    None => Ok(()),
+                }
+            }
+            ParameterOp::Replace {
+                mode,
+                pattern,
+                replacement,
+            } => write!(
+                f,
+                "{}{}/{}",
+                mode.as_str(),
+                pattern.source(),
+                replacement.source()
+            ),
+            ParameterOp::UpperCase { all } => write!(f, "{}", twice("^", *all)),
+            ParameterOp::LowerCase { all } => write!(f, "{}", twice(",", *all)),
+            ParameterOp::Invalid(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+/// 由若干片段组成的单词
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Word {
+    pub parts: Vec<WordPart>,
+}
+
+impl Word {
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+
+    /// 去除引号后的文本（不做任何展开）
+    pub fn text(&self) -> String {
+        self.parts.iter().map(part_text).collect()
+    }
+
+    /// 保留引号的源文本，用于显示函数定义和作业
+    pub fn source(&self) -> String {
+        self.parts
+            .iter()
+            .map(|part| part_source(part, false))
+            .collect()
+    }
+
+    /// 单词完全未加引号且不含展开时返回其文本
+    pub fn unquoted_text(&self) -> Option<&str> {
+        match self.parts.as_slice() {
+            [WordPart::Literal(s)] => Some(s),
+            _ => None,
+        }
+    }
+
+    /// 开始一个新的片段，空引号 "" 也会构成单词
+    fn open(&mut self, part: WordPart) {
+        self.parts.push(part);
+    }
+
+    fn push_literal(&mut self, ch: char) {
+        push_char(&mut self.parts, ch);
+    }
+
+    fn push_single_quoted(&mut self, ch: char) {
+        match self.parts.last_mut() {
+            Some(WordPart::SingleQuoted(s)) => s.push(ch),
+            _ => self.parts.push(WordPart::SingleQuoted(ch.to_string())),
+        }
+    }
+
+    /// 当前双引号片段中的内容
+    fn quoted_parts(&mut self) -> &mut Vec<WordPart> {
+        if !matches!(self.parts.last(), Some(WordPart::DoubleQuoted(_))) {
+            self.parts.push(WordPart::DoubleQuoted(Vec::new()));
+        }
+        match self.parts.last_mut() {
+            Some(WordPart::DoubleQuoted(parts)) => parts,
+            _ => unreachable!(),
+        }
+    }
+
+    fn push_double_quoted(&mut self, ch: char) {
+        push_char(self.quoted_parts(), ch);
+    }
+}
+
+/// 在片段序列末尾追加一个字面字符
+fn push_char(parts: &mut Vec<WordPart>, ch: char) {
+    match parts.last_mut() {
+        Some(WordPart::Literal(s)) => s.push(ch),
+        _ => parts.push(WordPart::Literal(ch.to_string())),
+    }
+}
+
+fn part_text(part: &WordPart) -> String {
+    match part {
+        WordPart::Literal(s) | WordPart::SingleQuoted(s) => s.clone(),
+        WordPart::DoubleQuoted(parts) => parts.iter().map(part_text).collect(),
+        WordPart::Parameter(parameter) => parameter.to_string(),
+        WordPart::CommandSubst(command) => format!("$({})", command),
+        WordPart::Arithmetic(parts) => {
+            let expr: String = parts.iter().map(part_text).collect();
+            format!("$(({}))", expr)
+        }
+    }
+}
+
+/// 片段的源文本，quoted 表示位于双引号内
+fn part_source(part: &WordPart, quoted: bool) -> String {
+    match part {
+        // 双引号内的 " \ $ ` 需要转义
+        WordPart::Literal(s) if quoted => s
+            .chars()
+            .flat_map(|ch| match ch {
+                '"' | '\\' | '$' | '`' => vec!['\\', ch],
+                _ => vec![ch],
+            })
+            .collect(),
+        WordPart::Literal(s) => s.clone(),
+        // 单个转义字符仍用反斜杠表示
+        WordPart::SingleQuoted(s) if s.chars().count() == 1 => format!("\\{}", s),
+        WordPart::SingleQuoted(s) => format!("'{}'", s.replace('\'', "'\\''")),
+        WordPart::DoubleQuoted(parts) => {
+            let inner: String = parts.iter().map(|part| part_source(part, true)).collect();
+            format!("\"{}\"", inner)
+        }
+        WordPart::Arithmetic(parts) => {
+            let expr: String = parts.iter().map(|part| part_source(part, false)).collect();
+            format!("$(({}))", expr)
+        }
+        WordPart::Parameter(_) | WordPart::CommandSubst(_) => part_text(part),
+    }
+}
+
+/// 记录字节偏移和行号的字符流
+struct Cursor<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    offset: usize, // 下一个字符的字节偏移
+    line: usize,   // 下一个字符所在的行号
+}
+
+impl<'a> Cursor<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            chars: text.chars().peekable(),
+            offset: 0,
+            line: 1,
+        }
+    }
+
+    fn peek(&mut self) -> Option<&char> {
+        self.chars.peek()
+    }
+
+    fn next_if(&mut self, f: impl FnOnce(&char) -> bool) -> Option<char> {
+        if self.chars.peek().is_some_and(f) {
+            self.next()
+        } else {
+            None
+        }
+    }
+
+    fn next_if_eq(&mut self, expected: &char) -> Option<char> {
+        self.next_if(|c| c == expected)
+    }
+
+    /// 当前位置处的空范围
+    fn here(&self) -> Span {
+        Span {
+            start: self.offset,
+            end: self.offset,
+            line: self.line,
+        }
+    }
+
+    /// 从 start 到当前位置的范围
+    fn span_from(&self, start: Span) -> Span {
+        Span {
+            end: self.offset,
+            ..start
+        }
+    }
+}
+
+impl Iterator for Cursor<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let ch = self.chars.next()?;
+        self.offset += ch.len_utf8();
+        if ch == '\n' {
+            self.line += 1;
+        }
+        Some(ch)
+    }
 }
 
 /// 更符合Linux真实shell风格的词法分析器
-pub fn tokenize_line(line: &str) -> anyhow::Result<Vec<RawToken>> {
+pub fn tokenize_line(line: &str) -> anyhow::Result<Vec<Token>> {
     // todo 修改tokens为result
     let mut tokens = Vec::new();
-    let mut current_word = String::new();
-    let mut state = LexerState::Normal;
-    let mut chars = line.chars().peekable();
-    while let Some(ch) = chars.next() {
-        match state {
-            LexerState::Normal => {
-                match ch {
-                    // 空白字符
-                    ch if ch.is_whitespace() => {
-                        // 这块内容能不能消除重复
-                        if !current_word.is_empty() {
-                            tokens.push(RawToken::Word(current_word.clone()));
-                            current_word.clear();
-                        }
-                    }
-                    // 管道
-                    '|' => {
-                        if !current_word.is_empty() {
-                            tokens.push(RawToken::Word(current_word.clone()));
-                            current_word.clear();
-                        }
-                        tokens.push(RawToken::Pipe);
-                    }
-                    // 重定向操作符
-                    '>' | '<' => {
-                        if !current_word.is_empty() {
-                            tokens.push(parse_word(&current_word));
-                            current_word.clear();
-                        }
-                        let op = parse_redirect_op(ch, &mut chars);
-                        tokens.push(RawToken::Redirect(op));
-                    }
-                    // 引号
-                    '\'' => {
-                        state = LexerState::SingleQuote;
-                    }
-                    '"' => {
-                        state = LexerState::DoubleQuote;
-                    }
-                    // 转义字符
-                    '\\' => {
-                        state = LexerState::Escaping;
-                    }
-                    // 普通字符
-                    _ => {
-                        current_word.push(ch);
+    let mut current_word = Word::default();
+    let mut heredoc_start = 0; // 尚未读取正文的 here-document 从这里开始查找
+    let mut chars = Cursor::new(line);
+    let mut word_start = chars.here(); // 当前单词的起始位置
+    loop {
+        let start = chars.here();
+        let Some(ch) = chars.next() else {
+            break;
+        };
+        if current_word.is_empty() {
+            word_start = start;
+        }
+        let word_span = word_start.to(start);
+        match ch {
+            // 换行：结束当前命令，并读取本行登记的 here-document 正文
+            '\n' => {
+                push_word(&mut tokens, &mut current_word, word_span);
+                tokens.push(Token::new(RawToken::Newline, chars.span_from(start)));
+                read_heredoc_bodies(&mut tokens[heredoc_start..], &mut chars)?;
+                heredoc_start = tokens.len();
+            }
+            // 空白字符
+            ch if ch.is_whitespace() => {
+                push_word(&mut tokens, &mut current_word, word_span);
+            }
+            // 单词开头的 # 起始注释，直到行尾
+            '#' if current_word.is_empty() => while chars.next_if(|&c| c != '\n').is_some() {},
+            // 管道或 ||
+            '|' => {
+                push_word(&mut tokens, &mut current_word, word_span);
+                if chars.peek() == Some(&'|') {
+                    chars.next(); // 消耗下一个字符
+                    tokens.push(Token::new(RawToken::Or, chars.span_from(start)));
+                } else {
+                    tokens.push(Token::new(RawToken::Pipe, chars.span_from(start)));
+                }
+            }
+            // &&
+            '&' if chars.peek() == Some(&'&') => {
+                chars.next(); // 消耗下一个字符
+                push_word(&mut tokens, &mut current_word, word_span);
+                tokens.push(Token::new(RawToken::And, chars.span_from(start)));
+            }
+            // &> 和 &>>：同时重定向标准输出和标准错误
+            '&' if chars.peek() == Some(&'>') => {
+                chars.next();
+                push_word(&mut tokens, &mut current_word, word_span);
+                let op = if chars.next_if_eq(&'>').is_some() {
+                    RedirectOp::AppendBoth
+                } else {
+                    RedirectOp::OutBoth
+                };
+                tokens.push(Token::new(RawToken::Redirect(op), chars.span_from(start)));
+            }
+            // 后台执行
+            '&' => {
+                push_word(&mut tokens, &mut current_word, word_span);
+                tokens.push(Token::new(RawToken::Background, chars.span_from(start)));
+            }
+            // 括号，用于函数定义
+            '(' => {
+                push_word(&mut tokens, &mut current_word, word_span);
+                tokens.push(Token::new(RawToken::LParen, chars.span_from(start)));
+            }
+            ')' => {
+                push_word(&mut tokens, &mut current_word, word_span);
+                tokens.push(Token::new(RawToken::RParen, chars.span_from(start)));
+            }
+            // 命令分隔符和 case 分支结束符 ;; ;& ;;&
+            ';' => {
+                push_word(&mut tokens, &mut current_word, word_span);
+                let token = if chars.next_if_eq(&';').is_some() {
+                    if chars.next_if_eq(&'&').is_some() {
+                        RawToken::DoubleSemicolonAnd
+                    } else {
+                        RawToken::DoubleSemicolon
                     }
+                } else if chars.next_if_eq(&'&').is_some() {
+                    RawToken::SemicolonAnd
+                } else {
+                    RawToken::Semicolon
+                };
+                tokens.push(Token::new(token, chars.span_from(start)));
+            }
+            // 重定向操作符
+            '>' | '<' => {
+                if !current_word.is_empty() {
+                    let raw = parse_word(std::mem::take(&mut current_word));
+                    tokens.push(Token::new(raw, word_span));
                 }
+                let op = parse_redirect_op(ch, &mut chars);
+                tokens.push(Token::new(RawToken::Redirect(op), chars.span_from(start)));
             }
-            LexerState::SingleQuote => match ch {
-                '\'' => {
-                    state = LexerState::Normal;
+            // 引号，未闭合时交互式输入继续读取下一行
+            '\'' => read_single_quoted(&mut chars, &mut current_word, start)?,
+            '"' => read_double_quoted(&mut chars, &mut current_word, start)?,
+            // 转义字符
+            // 反斜杠加换行是行接续，两者都被删除
+            '\\' if chars.peek() == Some(&'\n') => {
+                chars.next();
+                // 输入在行接续处结束，需要下一行
+                if chars.peek().is_none() {
+                    return Err(LexError::TrailingBackslash.into());
                 }
-                _ => {
-                    current_word.push(ch);
+            }
+            '\\' => match chars.next() {
+                Some(ch) => current_word.push_single_quoted(ch),
+                None => return Err(LexError::TrailingBackslash.into()),
+            },
+            // 参数展开、命令替换和算术展开
+            '$' => match read_dollar(&mut chars, start)? {
+                Some(part) => current_word.open(part),
+                None => current_word.push_literal(ch),
+            },
+            '`' => {
+                let command = read_backquote(&mut chars, start)?;
+                current_word.open(WordPart::CommandSubst(command));
+            }
+            // 普通字符
+            _ => {
+                current_word.push_literal(ch);
+            }
+        }
+    }
+    // 处理最后一个单词
+    push_word(&mut tokens, &mut current_word, word_start.to(chars.here()));
+    // 输入结束时仍有未读取正文的 here-document，需要调用方继续读取
+    if let Some(delimiter) = pending_heredocs(&tokens[heredoc_start..]).next() {
+        return Err(LexError::UnterminatedHeredoc(delimiter.text()).into());
+    }
+    Ok(tokens)
+}
+
+/// 读取 ' 之后直到闭合 ' 的文本，start 为 ' 之前的位置
+fn read_single_quoted(chars: &mut Cursor, word: &mut Word, start: Span) -> Result<(), LexError> {
+    let start = chars.span_from(start);
+    word.open(WordPart::SingleQuoted(String::new()));
+    loop {
+        match chars.next() {
+            Some('\'') => return Ok(()),
+            Some(ch) => word.push_single_quoted(ch),
+            None => {
+                let kind = QuoteKind::Single;
+                return Err(LexError::UnterminatedQuote { kind, start });
+            }
+        }
+    }
+}
+
+/// 读取 " 之后直到闭合 " 的内容，其中的展开照常识别，
+/// \ 只转义 "、\、$、` 和换行（行接续），start 为 " 之前的位置
+fn read_double_quoted(chars: &mut Cursor, word: &mut Word, start: Span) -> Result<(), LexError> {
+    let quote_start = chars.span_from(start);
+    word.open(WordPart::DoubleQuoted(Vec::new()));
+    loop {
+        let start = chars.here();
+        match chars.next() {
+            Some('"') => return Ok(()),
+            Some('\\') => match chars.next() {
+                Some('\n') => {}
+                Some(ch @ ('"' | '\\' | '$' | '`')) => word.push_double_quoted(ch),
+                Some(ch) => {
+                    word.push_double_quoted('\\');
+                    word.push_double_quoted(ch);
                 }
+                None => break,
+            },
+            Some('$') => match read_dollar(chars, start)? {
+                Some(part) => word.quoted_parts().push(part),
+                None => word.push_double_quoted('$'),
             },
-            LexerState::DoubleQuote => match ch {
-                '"' => {
-                    state = LexerState::Normal;
+            Some('`') => {
+                let command = read_backquote(chars, start)?;
+                word.quoted_parts().push(WordPart::CommandSubst(command));
+            }
+            Some(ch) => word.push_double_quoted(ch),
+            None => break,
+        }
+    }
+    Err(LexError::UnterminatedQuote {
+        kind: QuoteKind::Double,
+        start: quote_start,
+    })
+}
+
+/// 尚未读取正文的 here-document 分隔符
+fn pending_heredocs(tokens: &[Token]) -> impl Iterator<Item = &Word> {
+    tokens
+        .windows(2)
+        .filter_map(|pair| match (&pair[0].raw, &pair[1].raw) {
+            (
+                RawToken::Redirect(RedirectOp::Heredoc | RedirectOp::HeredocStrip),
+                RawToken::Word(delimiter),
+            ) => Some(delimiter),
+            _ => None,
+        })
+}
+
+/// 按出现顺序读取每个 here-document 的正文，用正文替换分隔符记号
+fn read_heredoc_bodies(tokens: &mut [Token], chars: &mut Cursor) -> Result<(), LexError> {
+    for i in 1..tokens.len() {
+        let strip_tabs = match (&tokens[i - 1].raw, &tokens[i].raw) {
+            (RawToken::Redirect(RedirectOp::Heredoc), RawToken::Word(_)) => false,
+            (RawToken::Redirect(RedirectOp::HeredocStrip), RawToken::Word(_)) => true,
+            _ => continue,
+        };
+        let RawToken::Word(delimiter) = &tokens[i].raw else {
+            continue;
+        };
+        // 分隔符带引号时正文不做任何展开
+        let quoted = delimiter.unquoted_text().is_none();
+        let delimiter = delimiter.text();
+
+        let mut body = String::new();
+        loop {
+            if chars.peek().is_none() {
+                return Err(LexError::UnterminatedHeredoc(delimiter));
+            }
+            let line: String = chars.by_ref().take_while(|&c| c != '\n').collect();
+            let line = if strip_tabs {
+                line.trim_start_matches('\t')
+            } else {
+                &line
+            };
+            if line == delimiter {
+                break;
+            }
+            body.push_str(line);
+            body.push('\n');
+        }
+        let body = if quoted {
+            Word {
+                parts: vec![WordPart::SingleQuoted(body)],
+            }
+        } else {
+            heredoc_word(&body)
+        };
+        tokens[i].raw = RawToken::HeredocBody(body);
+    }
+    Ok(())
+}
+
+/// 未加引号的 here-document 正文：像双引号内一样展开参数和命令替换，
+/// 但 " 没有特殊含义，\ 只转义 $、`、\ 和换行
+fn heredoc_word(body: &str) -> Word {
+    Word {
+        parts: vec![WordPart::DoubleQuoted(expansion_parts(body))],
+    }
+}
+
+/// 把文本拆分为字面文本和展开片段，用于 here-document 正文和算术表达式
+fn expansion_parts(text: &str) -> Vec<WordPart> {
+    let mut parts = Vec::new();
+    let mut chars = Cursor::new(text);
+    loop {
+        let start = chars.here();
+        let Some(ch) = chars.next() else {
+            break;
+        };
+        match ch {
+            '\\' => match chars.peek() {
+                Some('$' | '`' | '\\') => push_char(&mut parts, chars.next().unwrap_or(ch)),
+                Some('\n') => {
+                    chars.next();
                 }
-                '\\' => {
-                    state = LexerState::DoubleQuoteEscaping;
+                _ => push_char(&mut parts, ch),
+            },
+            '$' => match read_dollar(&mut chars, start) {
+                Ok(Some(part)) => parts.push(part),
+                _ => push_char(&mut parts, ch),
+            },
+            '`' => {
+                let command = read_backquote(&mut chars, start).unwrap_or_default();
+                parts.push(WordPart::CommandSubst(command));
+            }
+            _ => push_char(&mut parts, ch),
+        }
+    }
+    parts
+}
+
+/// 读取 $ 之后的 $(...)、$((...))、${...}、$name 或特殊参数，$ 后不构成展开时返回 None
+fn read_dollar(chars: &mut Cursor, start: Span) -> Result<Option<WordPart>, LexError> {
+    let part = match chars.peek() {
+        Some('(') => {
+            chars.next();
+            if chars.next_if_eq(&'(').is_some() {
+                WordPart::Arithmetic(expansion_parts(&read_arithmetic(chars, start)?))
+            } else {
+                WordPart::CommandSubst(read_dollar_paren(chars, start)?)
+            }
+        }
+        Some('{') => {
+            chars.next();
+            WordPart::Parameter(parse_parameter(&read_brace(chars, start)?))
+        }
+        Some(&c) if "?$!#@*-".contains(c) || c.is_ascii_digit() => {
+            chars.next();
+            WordPart::Parameter(Parameter::new(c.to_string(), false))
+        }
+        Some(&c) if c.is_ascii_alphabetic() || c == '_' => {
+            let mut name = String::new();
+            while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                name.push(c);
+            }
+            WordPart::Parameter(Parameter::new(name, false))
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(part))
+}
+
+/// 读取 ${ 之后直到匹配的 } 的文本，跳过引号内和嵌套的括号
+fn read_brace(chars: &mut Cursor, start: Span) -> Result<String, LexError> {
+    let start = chars.span_from(start);
+    let mut text = String::new();
+    let mut depth = 0;
+    while let Some(ch) = chars.next() {
+        match ch {
+            '{' => depth += 1,
+            '}' if depth == 0 => return Ok(text),
+            '}' => depth -= 1,
+            _ if push_quoted(ch, chars, &mut text) => continue,
+            _ => {}
+        }
+        text.push(ch);
+    }
+    Err(LexError::UnterminatedQuote {
+        kind: QuoteKind::DollarBrace,
+        start,
+    })
+}
+
+/// ch 为 \、' 或 " 时，把转义的字符或引号内的文本原样追加到 text 并返回 true
+fn push_quoted(ch: char, chars: &mut Cursor, text: &mut String) -> bool {
+    match ch {
+        '\\' => {
+            text.push(ch);
+            text.extend(chars.next());
+        }
+        '\'' => {
+            text.push(ch);
+            for next in chars.by_ref() {
+                text.push(next);
+                if next == '\'' {
+                    break;
                 }
-                _ => {
-                    current_word.push(ch);
+            }
+        }
+        '"' => {
+            text.push(ch);
+            while let Some(next) = chars.next() {
+                text.push(next);
+                match next {
+                    '\\' => text.extend(chars.next()),
+                    '"' => break,
+                    _ => {}
                 }
+            }
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// 解析 ${...} 的内容：${#name}、${!name}、${name} 或 ${name<操作><操作数>}
+fn parse_parameter(text: &str) -> Parameter {
+    let invalid = || Parameter {
+        op: Some(ParameterOp::Invalid(text.to_string())),
+        ..Parameter::new(String::new(), true)
+    };
+    // 单独的 ${#} 是位置参数的个数
+    if let Some(name) = text.strip_prefix('#').filter(|name| !name.is_empty()) {
+        if parameter_name_len(name) != name.len() {
+            return invalid();
+        }
+        return Parameter {
+            op: Some(ParameterOp::Length),
+            ..Parameter::new(name.to_string(), true)
+        };
+    }
+    let (indirect, rest) = match text.strip_prefix('!').filter(|name| !name.is_empty()) {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let len = parameter_name_len(rest);
+    if len == 0 {
+        return invalid();
+    }
+    let (name, op) = rest.split_at(len);
+    let op = match op {
+        "" => None,
+        _ => match parse_parameter_op(op) {
+            Some(op) => Some(op),
+            None => return invalid(),
+        },
+    };
+    Parameter {
+        indirect,
+        op,
+        ..Parameter::new(name.to_string(), true)
+    }
+}
+
+/// 参数名的字节长度：变量名（可带数组下标）、位置参数或单个特殊参数，不是参数名时为 0
+fn parameter_name_len(text: &str) -> usize {
+    let Some(first) = text.chars().next() else {
+        return 0;
+    };
+    let len = if first.is_ascii_digit() {
+        text.find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(text.len())
+    } else if first.is_ascii_alphabetic() || first == '_' {
+        text.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(text.len())
+    } else if "?$!#@*-".contains(first) {
+        return 1;
+    } else {
+        return 0;
+    };
+    // 数组下标 [...]
+    match text[len..]
+        .strip_prefix('[')
+        .and_then(|rest| rest.find(']'))
+    {
+        Some(end) if !first.is_ascii_digit() => len + end + 2,
+        _ => len,
+    }
+}
+
+/// 解析参数名之后的操作符和操作数，无法识别时返回 None
+fn parse_parameter_op(text: &str) -> Option<ParameterOp> {
+    let word = |text: &str| operand_word(text).ok();
+    let (colon, rest) = match text.strip_prefix(':') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let mut chars = rest.chars();
+    let op = match (colon, chars.next()?, chars.as_str()) {
+        (_, '-', operand) => ParameterOp::Default {
+            colon,
+            word: word(operand)?,
+        },
+        (_, '=', operand) => ParameterOp::Assign {
+            colon,
+            word: word(operand)?,
+        },
+        (_, '?', operand) => ParameterOp::Error {
+            colon,
+            word: word(operand)?,
+        },
+        (_, '+', operand) => ParameterOp::Alternative {
+            colon,
+            word: word(operand)?,
+        },
+        // ${name:offset} 和 ${name:offset:length}
+        (true, _, _) => {
+            let (offset, length) = split_operand(rest, ':');
+            ParameterOp::Substring {
+                offset: word(offset)?,
+                length: match length {
+                    Some(length) => Some(word(length)?),
+                    None => None,
+                },
+            }
+        }
+        (false, '#', operand) => match operand.strip_prefix('#') {
+            Some(pattern) => ParameterOp::RemovePrefix {
+                longest: true,
+                pattern: word(pattern)?,
             },
-            LexerState::Escaping => {
-                current_word.push(ch);
-                state = LexerState::Normal;
-            }
-            LexerState::DoubleQuoteEscaping => {
-                // 在双引号内，只有特定字符需要转义
-                match ch {
-                    '"' | '\\' | '$' | '`' => {
-                        current_word.push(ch);
-                    }
-                    _ => {
-                        current_word.push('\\');
-                        current_word.push(ch);
-                    }
+            None => ParameterOp::RemovePrefix {
+                longest: false,
+                pattern: word(operand)?,
+            },
+        },
+        (false, '%', operand) => match operand.strip_prefix('%') {
+            Some(pattern) => ParameterOp::RemoveSuffix {
+                longest: true,
+                pattern: word(pattern)?,
+            },
+            None => ParameterOp::RemoveSuffix {
+                longest: false,
+                pattern: word(operand)?,
+            },
+        },
+        (false, '/', operand) => {
+            let mode = match operand.chars().next() {
+                Some('/') => ReplaceMode::All,
+                Some('#') => ReplaceMode::Prefix,
+                Some('%') => ReplaceMode::Suffix,
+                _ => ReplaceMode::First,
+            };
+            let operand = match mode {
+                ReplaceMode::First => operand,
+                _ => &operand[1..],
+            };
+            let (pattern, replacement) = split_operand(operand, '/');
+            ParameterOp::Replace {
+                mode,
+                pattern: word(pattern)?,
+                replacement: word(replacement.unwrap_or_default())?,
+            }
+        }
+        (false, '^', "") => ParameterOp::UpperCase { all: false },
+        (false, '^', "^") => ParameterOp::UpperCase { all: true },
+        (false, ',', "") => ParameterOp::LowerCase { all: false },
+        (false, ',', ",") => ParameterOp::LowerCase { all: true },
+        _ => return None,
+    };
+    Some(op)
+}
+
+/// 在第一个不在引号内且未转义的 separator 处分割操作数
+fn split_operand(text: &str, separator: char) -> (&str, Option<&str>) {
+    let mut chars = Cursor::new(text);
+    let mut skipped = String::new();
+    loop {
+        let start = chars.here();
+        let Some(ch) = chars.next() else {
+            return (text, None);
+        };
+        if ch == separator {
+            return (&text[..start.start], Some(&text[chars.here().start..]));
+        }
+        push_quoted(ch, &mut chars, &mut skipped);
+    }
+}
+
+/// ${...} 中的操作数：引号、转义和展开照常识别，空白等其他字符都按字面处理
+fn operand_word(text: &str) -> Result<Word, LexError> {
+    let mut word = Word::default();
+    let mut chars = Cursor::new(text);
+    loop {
+        let start = chars.here();
+        let Some(ch) = chars.next() else {
+            break;
+        };
+        match ch {
+            '\'' => read_single_quoted(&mut chars, &mut word, start)?,
+            '"' => read_double_quoted(&mut chars, &mut word, start)?,
+            '\\' => match chars.next() {
+                Some(ch) => word.push_single_quoted(ch),
+                None => word.push_literal(ch),
+            },
+            '$' => match read_dollar(&mut chars, start)? {
+                Some(part) => word.open(part),
+                None => word.push_literal(ch),
+            },
+            '`' => {
+                let command = read_backquote(&mut chars, start)?;
+                word.open(WordPart::CommandSubst(command));
+            }
+            _ => word.push_literal(ch),
+        }
+    }
+    Ok(word)
+}
+
+/// 读取 $(( 之后直到匹配的 )) 的表达式文本
+fn read_arithmetic(chars: &mut Cursor, start: Span) -> Result<String, LexError> {
+    let start = chars.span_from(start);
+    let mut expr = String::new();
+    let mut depth = 0;
+    while let Some(ch) = chars.next() {
+        match ch {
+            '(' => depth += 1,
+            ')' if depth == 0 && chars.next_if_eq(&')').is_some() => return Ok(expr),
+            ')' => depth -= 1,
+            _ => {}
+        }
+        expr.push(ch);
+    }
+    Err(LexError::UnterminatedQuote {
+        kind: QuoteKind::Arithmetic,
+        start,
+    })
+}
+
+/// 读取 $( 之后直到匹配的 ) 的命令文本，跳过引号内和嵌套的括号，start 为 $ 之前的位置
+fn read_dollar_paren(chars: &mut Cursor, start: Span) -> Result<String, LexError> {
+    let start = chars.span_from(start);
+    let mut command = String::new();
+    let mut depth = 1;
+    while let Some(ch) = chars.next() {
+        match ch {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(command);
                 }
-                state = LexerState::DoubleQuote;
             }
+            _ if push_quoted(ch, chars, &mut command) => continue,
+            _ => {}
         }
+        command.push(ch);
     }
-    // 处理最后一个单词
+    Err(LexError::UnterminatedQuote {
+        kind: QuoteKind::DollarParen,
+        start,
+    })
+}
+
+/// 读取反引号命令替换，\`、\\ 和 \$ 去掉转义后交给内层命令，start 为 ` 之前的位置
+fn read_backquote(chars: &mut Cursor, start: Span) -> Result<String, LexError> {
+    let start = chars.span_from(start);
+    let mut command = String::new();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '`' => return Ok(command),
+            '\\' => match chars.next() {
+                Some(next @ ('`' | '\\' | '$')) => command.push(next),
+                Some(next) => {
+                    command.push(ch);
+                    command.push(next);
+                }
+                None => command.push(ch),
+            },
+            _ => command.push(ch),
+        }
+    }
+    Err(LexError::UnterminatedQuote {
+        kind: QuoteKind::Backquote,
+        start,
+    })
+}
+
+/// 结束当前单词并加入记号列表
+fn push_word(tokens: &mut Vec<Token>, current_word: &mut Word, span: Span) {
     if !current_word.is_empty() {
-        tokens.push(RawToken::Word(current_word.clone()));
+        tokens.push(Token::new(
+            RawToken::Word(std::mem::take(current_word)),
+            span,
+        ));
     }
-    Ok(tokens)
 }
 
 /// 解析单词，识别IO编号
-fn parse_word(word: &str) -> RawToken {
-    // 检查是否为IO编号（仅数字，且在重定向前有意义）
-    if let Ok(num) = word.parse::<u8>() {
-        RawToken::IoNumber(num)
-    } else {
-        RawToken::Word(word.to_string())
+fn parse_word(word: Word) -> RawToken {
+    // 检查是否为IO编号（仅未加引号的数字，且在重定向前有意义）
+    let digits = word
+        .unquoted_text()
+        .filter(|text| text.bytes().all(|b| b.is_ascii_digit()));
+    match digits.map(str::parse::<u8>) {
+        Some(Ok(num)) => RawToken::IoNumber(num),
+        _ => RawToken::Word(word),
     }
 }
 
 /// 解析重定向操作符
-fn parse_redirect_op(
-    first_char: char,
-    chars: &mut std::iter::Peekable<std::str::Chars>,
-) -> RedirectOp {
+fn parse_redirect_op(first_char: char, chars: &mut Cursor) -> RedirectOp {
     match first_char {
         '>' => {
             match chars.peek() {
@@ -152,6 +1107,10 @@ fn parse_redirect_op(
                     chars.next(); // 消耗下一个字符
                     RedirectOp::DupOut
                 }
+                Some('|') => {
+                    chars.next();
+                    RedirectOp::Clobber
+                }
                 _ => RedirectOp::Out,
             }
         }
@@ -159,15 +1118,99 @@ fn parse_redirect_op(
             match chars.peek() {
                 Some('<') => {
                     chars.next(); // 消耗下一个字符
-                    RedirectOp::Heredoc
+                    if chars.peek() == Some(&'-') {
+                        chars.next();
+                        RedirectOp::HeredocStrip
+                    } else {
+                        RedirectOp::Heredoc
+                    }
                 }
                 Some('&') => {
                     chars.next(); // 消耗下一个字符
                     RedirectOp::DupIn
                 }
+                Some('>') => {
+                    chars.next();
+                    RedirectOp::ReadWrite
+                }
                 _ => RedirectOp::In,
             }
         }
         _ => unreachable!(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 词法分析一行并取出各个单词的片段
+    fn word_parts(line: &str) -> Vec<Vec<WordPart>> {
+        tokenize_line(line)
+            .unwrap()
+            .into_iter()
+            .filter_map(|token| match token.raw {
+                RawToken::Word(word) => Some(word.parts),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn parameter(name: &str) -> WordPart {
+        WordPart::Parameter(Parameter {
+            name: name.to_string(),
+            braced: false,
+            indirect: false,
+            op: None,
+        })
+    }
+
+    #[test]
+    fn quoted_segments() {
+        let words = word_parts(r#"a"b $c"'d'\e"#);
+        assert_eq!(
+            words[0],
+            vec![
+                WordPart::Literal("a".to_string()),
+                WordPart::DoubleQuoted(vec![WordPart::Literal("b ".to_string()), parameter("c")]),
+                WordPart::SingleQuoted("de".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn substitution_segments() {
+        let words = word_parts("$(echo hi)`pwd` \"$(a \"b\")\"");
+        assert_eq!(
+            words[0],
+            vec![
+                WordPart::CommandSubst("echo hi".to_string()),
+                WordPart::CommandSubst("pwd".to_string()),
+            ]
+        );
+        assert_eq!(
+            words[1],
+            vec![WordPart::DoubleQuoted(vec![WordPart::CommandSubst(
+                "a \"b\"".to_string()
+            )])]
+        );
+    }
+
+    #[test]
+    fn arithmetic_segment() {
+        let words = word_parts("x$((1 + $n))");
+        assert_eq!(
+            words[0],
+            vec![
+                WordPart::Literal("x".to_string()),
+                WordPart::Arithmetic(vec![WordPart::Literal("1 + ".to_string()), parameter("n")]),
+            ]
+        );
+        // $( ( 中间有空格时是命令替换中的子 shell
+        let words = word_parts("$( (echo) )");
+        assert_eq!(
+            words[0],
+            vec![WordPart::CommandSubst(" (echo) ".to_string())]
+        );
+    }
+}
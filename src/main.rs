@@ -1,12 +1,24 @@
 #[allow(unused_imports)]
 mod auto_completion;
 mod builtin_commands;
+mod cli;
 mod executor;
+mod expand;
+mod fd_table;
+mod glob;
 mod history;
+mod jobs;
 mod lexer;
+mod options;
 mod parse;
+mod path_cache;
+mod script;
+mod span;
+mod startup;
+mod state;
 mod utils;
-use std::{path::PathBuf, sync::LazyLock};
+mod variables;
+use std::sync::LazyLock;
 
 use auto_completion::MyCompleter;
 use executor::CommandHandlerFactory;
@@ -17,18 +29,24 @@ use rustyline::{
     history::FileHistory,
 };
 
-use crate::parse::{
-    CommandType, ExecutionContext, excuete_single_command, execute_pipeline, parse_command,
+use crate::{
+    parse::{ExecutionContext, ParseError, execute_command_type, is_incomplete, parse_command},
+    script::LineSource,
+    state::ShellState,
 };
 
-pub static GLOBAL_VEC: LazyLock<Vec<PathBuf>> = LazyLock::new(|| {
-    let path = std::env::var("PATH").unwrap_or("".to_string());
-    std::env::split_paths(&std::ffi::OsStr::new(&path)).collect::<Vec<_>>()
-});
 pub static HOME_DIR: LazyLock<String> =
     LazyLock::new(|| std::env::var("HOME").unwrap_or("".to_string()));
 
 fn main() -> anyhow::Result<()> {
+    let cli = match cli::parse_args(std::env::args()) {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("{}: {}", std::env::args().next().unwrap_or_default(), e);
+            std::process::exit(2);
+        }
+    };
+
     let config = Config::builder()
         .history_ignore_dups(false)?
         .completion_type(CompletionType::List) // 多候选时列出
@@ -36,19 +54,55 @@ fn main() -> anyhow::Result<()> {
         .build();
 
     let completer = MyCompleter;
-    let mut rl = Editor::with_config(config)?;
+    let mut rl = fd_table::with_user_fds_reserved(|| Editor::with_config(config))?;
     rl.set_completion_type(rustyline::CompletionType::List);
     rl.set_helper(Some(completer));
+    let mut state = ShellState::new();
+    cli.apply(&mut state);
+    state.interactive = cli.is_interactive();
+
+    // 非交互模式：执行 -c 字符串、脚本文件或标准输入中的命令
+    if cli.command.is_some() || cli.script.is_some() || !state.interactive {
+        let source = match (&cli.command, &cli.script) {
+            (Some(command), _) => LineSource::from_text(command),
+            (None, Some(path)) => match std::fs::read_to_string(path) {
+                Ok(text) => LineSource::from_text(&text),
+                Err(e) => {
+                    eprintln!("{}: {}", path, utils::strip_os_error(&e));
+                    std::process::exit(127);
+                }
+            },
+            (None, None) => LineSource::Stdin,
+        };
+        let name = state.shell_name.clone();
+        let mut context = ExecutionContext::new(&mut rl, &mut state);
+        startup::load_startup_files(&cli, &mut context);
+        let status = script::run_script(source, &name, cli.noexec, &mut context);
+        std::process::exit(status);
+    }
+
     history::read_history_file(&mut rl)?;
+    state.jobs.init_interactive();
+    startup::load_startup_files(&cli, &mut ExecutionContext::new(&mut rl, &mut state));
     loop {
-        match rl.readline("$ ") {
+        // 报告后台作业的状态变化
+        eprint!("{}", state.jobs.notify());
+        // 让补全器看到最新的 PATH
+        path_cache::command_cache().sync_path(state.vars.get("PATH").unwrap_or(""));
+        match rl
+            .readline("$ ")
+            .and_then(|line| read_continuation(line, &mut rl, &state))
+        {
             Ok(line) => {
                 let _ = rl.add_history_entry(line.as_str());
-                let _ = parse_and_handle_line(&line, &mut rl);
+                if let Err(e) = parse_and_handle_line(&line, &mut rl, &mut state) {
+                    eprintln!("{}", e);
+                }
             }
+            // Ctrl-C 只丢弃当前输入，重新显示提示符
             Err(ReadlineError::Interrupted) => {
                 println!("^C");
-                break;
+                state.last_status = 128 + libc::SIGINT;
             }
             Err(ReadlineError::Eof) => {
                 println!("^D");
@@ -60,12 +114,35 @@ fn main() -> anyhow::Result<()> {
             }
         }
     }
-    Ok(())
+    // 以最后一条命令的状态退出
+    std::process::exit(state.last_status);
+}
+
+/// 命令未结束时以 PS2 提示继续读取后续行，返回完整的多行命令
+fn read_continuation(
+    mut input: String,
+    rl: &mut Editor<MyCompleter, FileHistory>,
+    state: &ShellState,
+) -> rustyline::Result<String> {
+    while is_incomplete(&input) {
+        let prompt = state.vars.get("PS2").unwrap_or("> ").to_string();
+        match rl.readline(&prompt) {
+            Ok(next) => {
+                input.push('\n');
+                input.push_str(&next);
+            }
+            // 输入结束时交给 shell 报告未结束的命令
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(input)
 }
 
 fn parse_and_handle_line(
     line: &str,
     rl: &mut Editor<MyCompleter, FileHistory>,
+    state: &mut ShellState,
 ) -> anyhow::Result<()> {
     let line_trim = line.trim();
 
@@ -74,20 +151,28 @@ fn parse_and_handle_line(
         return Ok(());
     }
 
-    // 词法分析
-    let raw_tokens = crate::lexer::tokenize_line(line_trim)?;
-
-    // 语法分析
-    let command_type = parse_command(&raw_tokens);
+    // 词法、语法分析，未结束的多行命令已由 read_continuation 继续读取
+    // 语法错误不执行任何命令，状态为 2
+    let command_type = match crate::lexer::tokenize_line(line_trim)
+        .and_then(|raw_tokens| Ok(parse_command(&raw_tokens)?))
+    {
+        Ok(command_type) => command_type,
+        Err(e) => {
+            eprintln!("{}", e);
+            if let Some(span) = e.downcast_ref::<ParseError>().and_then(ParseError::span) {
+                eprintln!("{}", span.render(line_trim));
+            }
+            state.last_status = 2;
+            return Ok(());
+        }
+    };
 
-    // 创建执行上下文
-    let mut context = ExecutionContext::new(rl);
+    // 创建执行上下文，丢弃上一行中未处理的 Ctrl-C
+    state.flow = None;
+    let mut context = ExecutionContext::new(rl, state);
 
     // 执行命令
-    let _ = match command_type {
-        CommandType::Simple(command) => excuete_single_command(&command, &mut context)?,
-        CommandType::Pipeline(commands) => execute_pipeline(&commands, &mut context)?,
-    };
+    let _ = execute_command_type(&command_type, &mut context)?;
 
     Ok(())
 }
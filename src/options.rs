@@ -0,0 +1,81 @@
+use crate::glob::GlobOptions;
+
+/// 可通过 shopt 设置的选项名
+pub const SHOPT_NAMES: [&str; 4] = ["dotglob", "failglob", "globstar", "nullglob"];
+/// set 选项注册表：选项名及对应的单字母标志，按名称排序
+pub const SET_OPTIONS: [(&str, Option<char>); 5] = [
+    ("errexit", Some('e')),
+    ("noclobber", Some('C')),
+    ("nounset", Some('u')),
+    ("pipefail", None),
+    ("xtrace", Some('x')),
+];
+
+/// shell 选项
+#[derive(Debug, Clone, Default)]
+pub struct ShellOptions {
+    pub dotglob: bool,   // 通配符匹配隐藏文件
+    pub failglob: bool,  // 通配符无匹配时报错
+    pub globstar: bool,  // ** 递归匹配目录
+    pub nullglob: bool,  // 通配符无匹配时展开为空
+    pub errexit: bool,   // 命令失败时退出 (-e)
+    pub noclobber: bool, // > 不覆盖已存在的文件 (-C)
+    pub nounset: bool,   // 展开未设置的变量时报错 (-u)
+    pub pipefail: bool,  // 管道状态取最后一个失败命令的状态
+    pub xtrace: bool,    // 执行前打印命令 (-x)
+}
+
+impl ShellOptions {
+    /// 按名称访问 shopt 选项
+    pub fn shopt_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "dotglob" => Some(&mut self.dotglob),
+            "failglob" => Some(&mut self.failglob),
+            "globstar" => Some(&mut self.globstar),
+            "nullglob" => Some(&mut self.nullglob),
+            _ => None,
+        }
+    }
+
+    pub fn shopt(&self, name: &str) -> Option<bool> {
+        self.clone().shopt_mut(name).map(|value| *value)
+    }
+
+    /// 按名称访问 set -o 选项
+    pub fn set_o_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "errexit" => Some(&mut self.errexit),
+            "noclobber" => Some(&mut self.noclobber),
+            "nounset" => Some(&mut self.nounset),
+            "pipefail" => Some(&mut self.pipefail),
+            "xtrace" => Some(&mut self.xtrace),
+            _ => None,
+        }
+    }
+
+    /// 按单字母标志访问 set 选项
+    pub fn set_flag_mut(&mut self, flag: char) -> Option<&mut bool> {
+        let (name, _) = SET_OPTIONS.iter().find(|(_, f)| *f == Some(flag))?;
+        self.set_o_mut(name)
+    }
+
+    /// $- 的值：已开启选项的单字母标志
+    pub fn flags(&self) -> String {
+        SET_OPTIONS
+            .iter()
+            .filter(|(name, _)| self.set_o(name) == Some(true))
+            .filter_map(|(_, flag)| *flag)
+            .collect()
+    }
+
+    pub fn set_o(&self, name: &str) -> Option<bool> {
+        self.clone().set_o_mut(name).map(|value| *value)
+    }
+
+    pub fn glob_options(&self) -> GlobOptions {
+        GlobOptions {
+            dotglob: self.dotglob,
+            globstar: self.globstar,
+        }
+    }
+}
@@ -1,16 +1,30 @@
-use std::fs::File;
+use std::{
+    fmt,
+    fs::File,
+    io::{Seek, SeekFrom, Write},
+    os::unix::io::{AsRawFd, FromRawFd, RawFd},
+    rc::Rc,
+};
 
 use rustyline::{Editor, history::FileHistory};
 
 use crate::{
     auto_completion::MyCompleter,
     executor::CommandResult,
-    lexer::{RawToken, RedirectOp},
+    expand::{FatalError, expand_pattern, expand_word, expand_words},
+    fd_table::FdTable,
+    lexer::{LexError, RawToken, RedirectOp, Token, Word, WordPart, tokenize_line},
+    span::Span,
+    state::{Flow, ShellState},
+    utils::strip_os_error,
+    variables::is_valid_name,
 };
 #[derive(Debug, Clone)]
 pub struct Command {
-    pub argv: Vec<String>,
+    pub assignments: Vec<Assignment>, // 命令前的 NAME=value
+    pub argv: Vec<Word>,
     pub redirections: Vec<Redirection>, // 有序，决定语义
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -18,241 +32,1579 @@ pub struct Redirection {
     pub src_fd: Option<u8>, // None = 默认 fd（>, <）
     pub op: RedirectOp,
     pub target: RedirectTarget,
+    pub span: Span, // 包括 fd 编号和目标
+}
+
+/// 变量赋值 NAME=value
+#[derive(Debug, Clone)]
+pub struct Assignment {
+    pub name: String,
+    pub value: Word,
 }
 
 #[derive(Debug, Clone)]
 pub enum RedirectTarget {
-    File(String), // > file
-    Fd(u8),       // 2>&1
-    Close,        // 2>&-
-    #[allow(dead_code)]
-    Heredoc(String),
+    File(Word),    // > file
+    Fd(u8),        // 2>&1
+    Close,         // 2>&-
+    Heredoc(Word), // << 的正文，展开后作为输入
 }
 
-/// 命令类型：简单命令或管道命令
+/// 命令类型：简单命令、管道命令或命令列表
 #[derive(Debug, Clone)]
 pub enum CommandType {
     Simple(Command),
-    Pipeline(Vec<Command>), // 管道连接的多个命令
+    Pipeline(Vec<CommandType>), // 管道连接的多个简单命令或复合命令
+    Not(Box<CommandType>),      // ! pipeline：反转管道的退出状态
+    AndOr {
+        first: Box<CommandType>,
+        rest: Vec<(AndOrOp, CommandType)>, // && / || 连接的后续命令
+    },
+    List(Vec<CommandType>),       // ; 分隔的命令序列
+    Background(Box<CommandType>), // 以 & 结尾，在后台作业中执行
+    FunctionDef {
+        name: String,
+        body: Rc<CommandType>, // 复合命令，通常为 { list; }
+    },
+    Compound {
+        command: Box<CompoundCommand>,
+        redirections: Vec<Redirection>, // 作用于整个复合命令
+    },
+}
+
+/// 复合命令
+#[derive(Debug, Clone)]
+pub enum CompoundCommand {
+    If {
+        branches: Vec<(CommandType, CommandType)>, // if 和 elif 的条件及对应的命令
+        otherwise: Option<CommandType>,            // else
+    },
+    Loop {
+        condition: CommandType,
+        body: CommandType,
+        until: bool, // until 在条件失败时执行循环体
+    },
+    For {
+        name: String,
+        words: Option<Vec<Word>>, // 省略 in 时遍历位置参数
+        body: CommandType,
+    },
+    Case {
+        word: Word,
+        items: Vec<CaseItem>,
+    },
+    Subshell(CommandType), // ( list )：在 fork 出的子 shell 中执行
+    Group(CommandType),    // { list; }：在当前 shell 中执行
+}
+
+/// case 的一个分支：pattern | pattern) list ;;
+#[derive(Debug, Clone)]
+pub struct CaseItem {
+    pub patterns: Vec<Word>,
+    pub body: Option<CommandType>,
+    pub terminator: CaseTerminator,
+}
+
+/// case 分支的结束符
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CaseTerminator {
+    Break,       // ;;
+    FallThrough, // ;&：不测试模式，继续执行下一个分支
+    Continue,    // ;;&：继续测试后面分支的模式
+}
+
+/// 语法错误，span 为出错记号在源文本中的位置
+#[derive(Debug, thiserror::Error)]
+pub enum ParseError {
+    /// 输入提前结束，交互式输入时需要继续读取
+    #[error("syntax error: unexpected end of file")]
+    UnexpectedEof,
+    #[error("syntax error near unexpected token `{token}'")]
+    UnexpectedToken { token: String, span: Span },
+    #[error("`{name}': not a valid identifier")]
+    InvalidIdentifier { name: String, span: Span },
 }
 
-pub fn parse_command(tokens: &[RawToken]) -> CommandType {
-    let mut commands = Vec::new();
-    let mut current_tokens = Vec::new();
+impl ParseError {
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            ParseError::UnexpectedEof => None,
+            ParseError::UnexpectedToken { span, .. }
+            | ParseError::InvalidIdentifier { span, .. } => Some(*span),
+        }
+    }
+}
 
-    for token in tokens {
-        match token {
-            RawToken::Pipe => {
-                if !current_tokens.is_empty() {
-                    commands.push(parse_simple_command(&current_tokens));
-                    current_tokens.clear();
+/// 条件连接符
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AndOrOp {
+    And, // &&
+    Or,  // ||
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        for assignment in &self.assignments {
+            parts.push(format!("{}={}", assignment.name, assignment.value.source()));
+        }
+        parts.extend(self.argv.iter().map(Word::source));
+        parts.extend(self.redirections.iter().map(Redirection::to_string));
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+impl fmt::Display for Redirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fd = self.src_fd.map(|fd| fd.to_string()).unwrap_or_default();
+        let op = token_text(&RawToken::Redirect(self.op));
+        let target = match &self.target {
+            RedirectTarget::File(word) => word.source(),
+            RedirectTarget::Fd(fd) => fd.to_string(),
+            RedirectTarget::Close => "-".to_string(),
+            RedirectTarget::Heredoc(_) => String::new(),
+        };
+        write!(f, "{}{}{}", fd, op, target)
+    }
+}
+
+impl fmt::Display for CompoundCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompoundCommand::If {
+                branches,
+                otherwise,
+            } => {
+                for (i, (condition, body)) in branches.iter().enumerate() {
+                    let keyword = if i == 0 { "if" } else { " elif" };
+                    write!(
+                        f,
+                        "{} {} then {}",
+                        keyword,
+                        terminated(condition),
+                        terminated(body)
+                    )?;
+                }
+                if let Some(body) = otherwise {
+                    write!(f, " else {}", terminated(body))?;
                 }
+                write!(f, " fi")
+            }
+            CompoundCommand::Loop {
+                condition,
+                body,
+                until,
+            } => {
+                let keyword = if *until { "until" } else { "while" };
+                write!(
+                    f,
+                    "{} {} do {} done",
+                    keyword,
+                    terminated(condition),
+                    terminated(body)
+                )
             }
-            _ => {
-                current_tokens.push(token.clone());
+            CompoundCommand::For { name, words, body } => {
+                write!(f, "for {}", name)?;
+                if let Some(words) = words {
+                    write!(f, " in")?;
+                    for word in words {
+                        write!(f, " {}", word.source())?;
+                    }
+                }
+                write!(f, "; do {} done", terminated(body))
+            }
+            CompoundCommand::Case { word, items } => {
+                write!(f, "case {} in", word.source())?;
+                for item in items {
+                    let patterns: Vec<String> = item.patterns.iter().map(Word::source).collect();
+                    write!(f, " {})", patterns.join(" | "))?;
+                    if let Some(body) = &item.body {
+                        write!(f, " {}", body)?;
+                    }
+                    let terminator = match item.terminator {
+                        CaseTerminator::Break => ";;",
+                        CaseTerminator::FallThrough => ";&",
+                        CaseTerminator::Continue => ";;&",
+                    };
+                    write!(f, "{}", terminator)?;
+                }
+                write!(f, " esac")
             }
+            CompoundCommand::Subshell(body) => write!(f, "( {} )", body),
+            CompoundCommand::Group(body) => write!(f, "{{ {} }}", terminated(body)),
         }
     }
+}
 
-    // 处理最后一个命令
-    if !current_tokens.is_empty() {
-        commands.push(parse_simple_command(&current_tokens));
+/// 命令序列的文本，末尾加上 ;（以 & 结尾时除外），用于拼接保留字
+fn terminated(command_type: &CommandType) -> String {
+    let last = match command_type {
+        CommandType::List(items) => items.last(),
+        command_type => Some(command_type),
+    };
+    match last {
+        Some(CommandType::Background(_)) => command_type.to_string(),
+        _ => format!("{};", command_type),
     }
+}
 
-    if commands.len() == 1 {
-        CommandType::Simple(commands.remove(0))
-    } else {
-        CommandType::Pipeline(commands)
+/// 还原为命令文本，用于作业列表等显示
+impl fmt::Display for CommandType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandType::Simple(command) => write!(f, "{}", command),
+            CommandType::Pipeline(commands) => {
+                let texts: Vec<String> = commands.iter().map(CommandType::to_string).collect();
+                write!(f, "{}", texts.join(" | "))
+            }
+            CommandType::AndOr { first, rest } => {
+                write!(f, "{}", first)?;
+                for (op, command_type) in rest {
+                    let op = match op {
+                        AndOrOp::And => "&&",
+                        AndOrOp::Or => "||",
+                    };
+                    write!(f, " {} {}", op, command_type)?;
+                }
+                Ok(())
+            }
+            CommandType::List(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 && !matches!(items[i - 1], CommandType::Background(_)) {
+                        write!(f, ";")?;
+                    }
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                Ok(())
+            }
+            CommandType::Background(command_type) => write!(f, "{} &", command_type),
+            CommandType::Not(command_type) => write!(f, "! {}", command_type),
+            CommandType::FunctionDef { name, body } => {
+                write!(f, "{}() {}", name, body)
+            }
+            CommandType::Compound {
+                command,
+                redirections,
+            } => {
+                write!(f, "{}", command)?;
+                for redirection in redirections {
+                    write!(f, " {}", redirection)?;
+                }
+                Ok(())
+            }
+        }
     }
 }
 
-pub fn parse_simple_command(tokens: &[RawToken]) -> Command {
+/// 递归下降语法分析器
+///
+/// list     := and_or ((';' | '&' | '\n') and_or)* [';' | '&' | '\n']
+/// and_or   := pipeline (('&&' | '||') linebreak pipeline)*
+/// pipeline := function | command ('|' linebreak command)*
+/// command  := compound redirect* | simple
+/// compound := if | while | until | for | case | '(' list ')' | '{' list '}'
+/// function := ['function'] name ['(' ')'] linebreak compound redirect*
+struct Parser<'t> {
+    tokens: &'t [Token],
+    pos: usize,
+}
+
+impl<'t> Parser<'t> {
+    fn peek(&self) -> Option<&'t RawToken> {
+        self.tokens.get(self.pos).map(|token| &token.raw)
+    }
+
+    /// 跳过空行
+    fn skip_newlines(&mut self) {
+        while let Some(RawToken::Newline) = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    /// 当前位置的未加引号单词，用于识别保留字
+    fn peek_reserved(&self) -> Option<&'t str> {
+        match self.peek() {
+            Some(RawToken::Word(w)) => w.unquoted_text(),
+            _ => None,
+        }
+    }
+
+    /// 当前记号处的语法错误，输入结束时需要更多输入
+    fn unexpected(&self) -> ParseError {
+        match self.peek() {
+            Some(token) => ParseError::UnexpectedToken {
+                token: token_text(token),
+                span: self.tokens[self.pos].span,
+            },
+            None => ParseError::UnexpectedEof,
+        }
+    }
+
+    /// 读取期望的保留字
+    fn expect_reserved(&mut self, word: &str) -> Result<(), ParseError> {
+        if self.peek_reserved() != Some(word) {
+            return Err(self.unexpected());
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// 是否位于 case 分支的结束符
+    fn at_case_terminator(&self) -> bool {
+        matches!(
+            self.peek(),
+            Some(RawToken::DoubleSemicolon | RawToken::SemicolonAnd | RawToken::DoubleSemicolonAnd)
+        )
+    }
+
+    /// 解析命令序列，遇到 terminators 中的保留字时停止
+    fn parse_list(&mut self, terminators: &[&str]) -> Result<CommandType, ParseError> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_newlines();
+            // ) 结束子 shell 中的命令序列
+            if matches!(self.peek(), None | Some(RawToken::RParen)) || self.at_case_terminator() {
+                break;
+            }
+            if let Some(word) = self.peek_reserved() {
+                if terminators.contains(&word) {
+                    break;
+                }
+                if CLOSING_RESERVED_WORDS.contains(&word) {
+                    return Err(self.unexpected());
+                }
+            }
+            let item = self.parse_and_or()?;
+            match self.peek() {
+                Some(RawToken::Background) => {
+                    self.pos += 1;
+                    items.push(CommandType::Background(Box::new(item)));
+                }
+                Some(RawToken::Semicolon | RawToken::Newline) => {
+                    self.pos += 1;
+                    items.push(item);
+                }
+                // case 分支在 ;; 等处结束，由 parse_case 读取结束符
+                Some(RawToken::RParen) => {
+                    items.push(item);
+                    break;
+                }
+                _ if self.at_case_terminator() => {
+                    items.push(item);
+                    break;
+                }
+                Some(_) => return Err(self.unexpected()),
+                None => {
+                    items.push(item);
+                    break;
+                }
+            }
+        }
+        Ok(if items.len() == 1 {
+            items.remove(0)
+        } else {
+            CommandType::List(items)
+        })
+    }
+
+    fn parse_and_or(&mut self) -> Result<CommandType, ParseError> {
+        let first = self.parse_pipeline()?;
+        let mut rest = Vec::new();
+        loop {
+            let op = match self.peek() {
+                Some(RawToken::And) => AndOrOp::And,
+                Some(RawToken::Or) => AndOrOp::Or,
+                _ => break,
+            };
+            self.pos += 1;
+            self.skip_newlines();
+            rest.push((op, self.parse_pipeline()?));
+        }
+        Ok(if rest.is_empty() {
+            first
+        } else {
+            CommandType::AndOr {
+                first: Box::new(first),
+                rest,
+            }
+        })
+    }
+
+    fn parse_pipeline(&mut self) -> Result<CommandType, ParseError> {
+        if self.peek_reserved() == Some("!") {
+            self.pos += 1;
+            return Ok(CommandType::Not(Box::new(self.parse_pipeline()?)));
+        }
+        if let Some(function) = self.parse_function()? {
+            return Ok(function);
+        }
+        let mut commands = vec![self.parse_command()?];
+        while let Some(RawToken::Pipe) = self.peek() {
+            self.pos += 1;
+            self.skip_newlines();
+            commands.push(self.parse_command()?);
+        }
+        Ok(if commands.len() == 1 {
+            commands.remove(0)
+        } else {
+            CommandType::Pipeline(commands)
+        })
+    }
+
+    /// 管道中的一个命令：复合命令或简单命令
+    fn parse_command(&mut self) -> Result<CommandType, ParseError> {
+        match self.parse_compound()? {
+            Some(compound) => Ok(compound),
+            None => Ok(CommandType::Simple(self.parse_simple()?)),
+        }
+    }
+
+    /// name() { list; } 或 function name { list; }，不是函数定义时返回 None
+    fn parse_function(&mut self) -> Result<Option<CommandType>, ParseError> {
+        let tokens = self.tokens;
+        let raw_at = |pos: usize| tokens.get(pos).map(|token| &token.raw);
+        let name_at = |pos: usize| match raw_at(pos) {
+            Some(RawToken::Word(w)) => w.unquoted_text().filter(|name| is_function_name(name)),
+            _ => None,
+        };
+        let has_parens = |pos: usize| {
+            matches!(
+                (raw_at(pos), raw_at(pos + 1)),
+                (Some(RawToken::LParen), Some(RawToken::RParen))
+            )
+        };
+        let name = if self.peek_reserved() == Some("function") {
+            let Some(name) = name_at(self.pos + 1) else {
+                return Ok(None);
+            };
+            self.pos += 2;
+            if has_parens(self.pos) {
+                self.pos += 2;
+            }
+            name
+        } else {
+            match name_at(self.pos) {
+                Some(name) if has_parens(self.pos + 1) => {
+                    self.pos += 3;
+                    name
+                }
+                _ => return Ok(None),
+            }
+        };
+
+        // 函数体可以是任意复合命令，通常为 { list; }
+        self.skip_newlines();
+        let Some(body) = self.parse_compound()? else {
+            return Err(self.unexpected());
+        };
+        Ok(Some(CommandType::FunctionDef {
+            name: name.to_string(),
+            body: Rc::new(body),
+        }))
+    }
+
+    /// 复合命令及其后的重定向，不是复合命令时返回 None
+    fn parse_compound(&mut self) -> Result<Option<CommandType>, ParseError> {
+        if let Some(RawToken::LParen) = self.peek() {
+            self.pos += 1;
+            let body = self.parse_compound_list(&[])?;
+            match self.peek() {
+                Some(RawToken::RParen) => self.pos += 1,
+                _ => return Err(self.unexpected()),
+            }
+            let redirections = self.parse_redirections()?;
+            return Ok(Some(CommandType::Compound {
+                command: Box::new(CompoundCommand::Subshell(body)),
+                redirections,
+            }));
+        }
+        let command = match self.peek_reserved() {
+            Some("{") => {
+                self.pos += 1;
+                let body = self.parse_compound_list(&["}"])?;
+                self.expect_reserved("}")?;
+                CompoundCommand::Group(body)
+            }
+            Some("if") => self.parse_if()?,
+            Some("while") => self.parse_loop(false)?,
+            Some("until") => self.parse_loop(true)?,
+            Some("for") => self.parse_for()?,
+            Some("case") => self.parse_case()?,
+            _ => return Ok(None),
+        };
+        let redirections = self.parse_redirections()?;
+        Ok(Some(CommandType::Compound {
+            command: Box::new(command),
+            redirections,
+        }))
+    }
+
+    /// 复合命令中的命令序列，不能为空
+    fn parse_compound_list(&mut self, terminators: &[&str]) -> Result<CommandType, ParseError> {
+        let list = self.parse_list(terminators)?;
+        if matches!(&list, CommandType::List(items) if items.is_empty()) {
+            return Err(self.unexpected());
+        }
+        Ok(list)
+    }
+
+    /// if list then list [elif list then list]... [else list] fi
+    fn parse_if(&mut self) -> Result<CompoundCommand, ParseError> {
+        self.pos += 1;
+        let mut branches = Vec::new();
+        let mut otherwise = None;
+        loop {
+            let condition = self.parse_compound_list(&["then"])?;
+            self.expect_reserved("then")?;
+            let body = self.parse_compound_list(&["elif", "else", "fi"])?;
+            branches.push((condition, body));
+            match self.peek_reserved() {
+                Some("elif") => self.pos += 1,
+                Some("else") => {
+                    self.pos += 1;
+                    otherwise = Some(self.parse_compound_list(&["fi"])?);
+                    break;
+                }
+                _ => break,
+            }
+        }
+        self.expect_reserved("fi")?;
+        Ok(CompoundCommand::If {
+            branches,
+            otherwise,
+        })
+    }
+
+    /// while list do list done / until list do list done
+    fn parse_loop(&mut self, until: bool) -> Result<CompoundCommand, ParseError> {
+        self.pos += 1;
+        let condition = self.parse_compound_list(&["do"])?;
+        let body = self.parse_do_group()?;
+        Ok(CompoundCommand::Loop {
+            condition,
+            body,
+            until,
+        })
+    }
+
+    /// for name [in word...] do list done
+    fn parse_for(&mut self) -> Result<CompoundCommand, ParseError> {
+        self.pos += 1;
+        let name = match self.peek() {
+            Some(RawToken::Word(w)) if w.unquoted_text().is_some_and(is_valid_name) => w.text(),
+            Some(RawToken::Word(w)) => {
+                return Err(ParseError::InvalidIdentifier {
+                    name: w.text(),
+                    span: self.tokens[self.pos].span,
+                });
+            }
+            _ => return Err(self.unexpected()),
+        };
+        self.pos += 1;
+        self.skip_newlines();
+        let words = if self.peek_reserved() == Some("in") {
+            self.pos += 1;
+            let mut words = Vec::new();
+            while let Some(RawToken::Word(w)) = self.peek() {
+                words.push(w.clone());
+                self.pos += 1;
+            }
+            match self.peek() {
+                Some(RawToken::Semicolon | RawToken::Newline) => self.pos += 1,
+                _ => return Err(self.unexpected()),
+            }
+            Some(words)
+        } else {
+            if let Some(RawToken::Semicolon) = self.peek() {
+                self.pos += 1;
+            }
+            None
+        };
+        let body = self.parse_do_group()?;
+        Ok(CompoundCommand::For { name, words, body })
+    }
+
+    /// do list done
+    fn parse_do_group(&mut self) -> Result<CommandType, ParseError> {
+        self.skip_newlines();
+        self.expect_reserved("do")?;
+        let body = self.parse_compound_list(&["done"])?;
+        self.expect_reserved("done")?;
+        Ok(body)
+    }
+
+    /// case word in [(] pattern [| pattern]...) [list] ;; ... esac
+    fn parse_case(&mut self) -> Result<CompoundCommand, ParseError> {
+        self.pos += 1;
+        let word = match self.peek() {
+            Some(RawToken::Word(w)) => w.clone(),
+            _ => return Err(self.unexpected()),
+        };
+        self.pos += 1;
+        self.skip_newlines();
+        self.expect_reserved("in")?;
+
+        let mut items = Vec::new();
+        loop {
+            self.skip_newlines();
+            if self.peek_reserved() == Some("esac") {
+                break;
+            }
+            if let Some(RawToken::LParen) = self.peek() {
+                self.pos += 1;
+            }
+            let mut patterns = Vec::new();
+            loop {
+                let Some(RawToken::Word(pattern)) = self.peek() else {
+                    return Err(self.unexpected());
+                };
+                patterns.push(pattern.clone());
+                self.pos += 1;
+                match self.peek() {
+                    Some(RawToken::Pipe) => self.pos += 1,
+                    Some(RawToken::RParen) => {
+                        self.pos += 1;
+                        break;
+                    }
+                    _ => return Err(self.unexpected()),
+                }
+            }
+            let body = self.parse_list(&["esac"])?;
+            let body =
+                (!matches!(&body, CommandType::List(items) if items.is_empty())).then_some(body);
+            let terminator = match self.peek() {
+                Some(RawToken::DoubleSemicolon) => CaseTerminator::Break,
+                Some(RawToken::SemicolonAnd) => CaseTerminator::FallThrough,
+                Some(RawToken::DoubleSemicolonAnd) => CaseTerminator::Continue,
+                // 最后一个分支可以省略 ;;
+                _ => {
+                    items.push(CaseItem {
+                        patterns,
+                        body,
+                        terminator: CaseTerminator::Break,
+                    });
+                    break;
+                }
+            };
+            self.pos += 1;
+            items.push(CaseItem {
+                patterns,
+                body,
+                terminator,
+            });
+        }
+        self.expect_reserved("esac")?;
+        Ok(CompoundCommand::Case { word, items })
+    }
+
+    /// 复合命令之后的重定向
+    fn parse_redirections(&mut self) -> Result<Vec<Redirection>, ParseError> {
+        let start = self.pos;
+        loop {
+            match self.peek() {
+                Some(RawToken::IoNumber(_)) => self.pos += 1,
+                Some(RawToken::Redirect(_)) => self.parse_redirect_target()?,
+                _ => break,
+            }
+        }
+        Ok(parse_simple_command(&self.tokens[start..self.pos])?.redirections)
+    }
+
+    fn parse_simple(&mut self) -> Result<Command, ParseError> {
+        let start = self.pos;
+        while let Some(token) = self.peek() {
+            match token {
+                _ if is_separator(token) => break,
+                RawToken::Redirect(_) => self.parse_redirect_target()?,
+                _ => self.pos += 1,
+            }
+        }
+        if start == self.pos {
+            return Err(self.unexpected());
+        }
+        parse_simple_command(&self.tokens[start..self.pos])
+    }
+
+    /// 跳过重定向操作符，其后必须是目标单词
+    fn parse_redirect_target(&mut self) -> Result<(), ParseError> {
+        self.pos += 1;
+        match self.peek() {
+            Some(RawToken::Word(_) | RawToken::HeredocBody(_)) => {
+                self.pos += 1;
+                Ok(())
+            }
+            // 行尾时和 bash 一样报告 newline
+            None => Err(missing_target(self.tokens, self.pos)),
+            Some(_) => Err(self.unexpected()),
+        }
+    }
+}
+
+/// 保留字，只在命令开头识别
+const RESERVED_WORDS: [&str; 17] = [
+    "if", "then", "elif", "else", "fi", "while", "until", "for", "in", "do", "done", "case",
+    "esac", "function", "{", "}", "!",
+];
+/// 只能出现在复合命令中间或末尾、不能作为命令开头的保留字
+const CLOSING_RESERVED_WORDS: [&str; 8] = ["then", "elif", "else", "fi", "do", "done", "esac", "}"];
+
+/// 函数名不能含有 $、` 和 =，也不能是保留字
+fn is_function_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['$', '=', '`']) && !RESERVED_WORDS.contains(&name)
+}
+
+/// 是否为命令之间的分隔符
+fn is_separator(token: &RawToken) -> bool {
+    matches!(
+        token,
+        RawToken::Pipe
+            | RawToken::And
+            | RawToken::Or
+            | RawToken::Semicolon
+            | RawToken::DoubleSemicolon
+            | RawToken::SemicolonAnd
+            | RawToken::DoubleSemicolonAnd
+            | RawToken::Background
+            | RawToken::Newline
+            | RawToken::LParen
+            | RawToken::RParen
+    )
+}
+
+/// 用于错误信息的记号文本
+fn token_text(token: &RawToken) -> String {
+    match token {
+        RawToken::Word(w) => w.text(),
+        RawToken::Pipe => "|".to_string(),
+        RawToken::And => "&&".to_string(),
+        RawToken::Or => "||".to_string(),
+        RawToken::Semicolon => ";".to_string(),
+        RawToken::DoubleSemicolon => ";;".to_string(),
+        RawToken::SemicolonAnd => ";&".to_string(),
+        RawToken::DoubleSemicolonAnd => ";;&".to_string(),
+        RawToken::Background => "&".to_string(),
+        RawToken::LParen => "(".to_string(),
+        RawToken::RParen => ")".to_string(),
+        RawToken::Newline => "newline".to_string(),
+        RawToken::HeredocBody(w) => w.text(),
+        RawToken::IoNumber(n) => n.to_string(),
+        RawToken::Redirect(op) => match op {
+            RedirectOp::Out => ">",
+            RedirectOp::OutAppend => ">>",
+            RedirectOp::In => "<",
+            RedirectOp::Heredoc => "<<",
+            RedirectOp::HeredocStrip => "<<-",
+            RedirectOp::DupOut => ">&",
+            RedirectOp::DupIn => "<&",
+            RedirectOp::ReadWrite => "<>",
+            RedirectOp::Clobber => ">|",
+            RedirectOp::OutBoth => "&>",
+            RedirectOp::AppendBoth => "&>>",
+        }
+        .to_string(),
+    }
+}
+
+pub fn parse_command(tokens: &[Token]) -> Result<CommandType, ParseError> {
+    let mut parser = Parser { tokens, pos: 0 };
+    let command_type = parser.parse_list(&[])?;
+    // 例如顶层的 ;;
+    if parser.peek().is_some() {
+        return Err(parser.unexpected());
+    }
+    Ok(command_type)
+}
+
+/// 解析简单命令的记号
+pub fn parse_simple_command(tokens: &[Token]) -> Result<Command, ParseError> {
+    let mut assignments = Vec::new();
     let mut argv = Vec::new();
     let mut redirections = Vec::new();
 
     let mut i = 0;
     while i < tokens.len() {
-        match &tokens[i] {
+        match &tokens[i].raw {
             RawToken::Word(w) => {
-                argv.push(w.clone());
+                // 命令名之前的 NAME=value 视为赋值
+                match parse_assignment(w) {
+                    Some(assignment) if argv.is_empty() => assignments.push(assignment),
+                    _ => argv.push(w.clone()),
+                }
                 i += 1;
             }
 
             RawToken::IoNumber(fd) => {
                 let src_fd = Some(*fd);
 
-                match tokens.get(i + 1) {
+                match tokens.get(i + 1).map(|token| &token.raw) {
                     Some(RawToken::Redirect(op)) => {
-                        let target = parse_redirect_target(&tokens[i + 2]);
+                        let target = parse_redirect_target(*op, tokens, i + 2)?;
                         redirections.push(Redirection {
                             src_fd,
                             op: *op,
                             target,
+                            span: tokens[i].span.to(tokens[i + 2].span),
                         });
                         i += 3;
                     }
-                    _ => panic!("io number not followed by redirect"),
+                    _ => return Err(missing_target(tokens, i + 1)),
                 }
             }
 
             RawToken::Redirect(op) => {
                 let src_fd = None;
-                let target = parse_redirect_target(&tokens[i + 1]);
+                let target = parse_redirect_target(*op, tokens, i + 1)?;
 
                 redirections.push(Redirection {
                     src_fd,
                     op: *op,
                     target,
+                    span: tokens[i].span.to(tokens[i + 1].span),
                 });
                 i += 2;
             }
 
-            _ => panic!("unexpected token"),
+            token => {
+                return Err(ParseError::UnexpectedToken {
+                    token: token_text(token),
+                    span: tokens[i].span,
+                });
+            }
         }
     }
 
-    Command { argv, redirections }
+    let span = match (tokens.first(), tokens.last()) {
+        (Some(first), Some(last)) => first.span.to(last.span),
+        _ => Span::default(),
+    };
+    Ok(Command {
+        assignments,
+        argv,
+        redirections,
+        span,
+    })
 }
 
-fn parse_redirect_target(token: &RawToken) -> RedirectTarget {
-    match token {
-        RawToken::Word(w) if w == "-" => RedirectTarget::Close,
-        RawToken::Word(w) => {
-            if let Ok(fd) = w.parse::<u8>() {
-                RedirectTarget::Fd(fd)
-            } else {
-                RedirectTarget::File(w.clone())
-            }
-        }
-        _ => panic!("invalid redirect target"),
+/// 识别赋值单词：等号前必须是未加引号的合法变量名
+fn parse_assignment(word: &Word) -> Option<Assignment> {
+    let Some(WordPart::Literal(first)) = word.parts.first() else {
+        return None;
+    };
+    let (name, rest) = first.split_once('=')?;
+    if !is_valid_name(name) {
+        return None;
+    }
+    let mut value = Word::default();
+    if !rest.is_empty() {
+        value.parts.push(WordPart::Literal(rest.to_string()));
+    }
+    value.parts.extend_from_slice(&word.parts[1..]);
+    Some(Assignment {
+        name: name.to_string(),
+        value,
+    })
+}
+
+/// 解析 tokens[i] 处的重定向目标
+fn parse_redirect_target(
+    op: RedirectOp,
+    tokens: &[Token],
+    i: usize,
+) -> Result<RedirectTarget, ParseError> {
+    let is_dup = matches!(op, RedirectOp::DupOut | RedirectOp::DupIn);
+    Ok(match tokens.get(i).map(|token| &token.raw) {
+        Some(RawToken::HeredocBody(body)) => RedirectTarget::Heredoc(body.clone()),
+        // 只有 >& 和 <& 的目标才解释为 fd 编号或关闭
+        Some(RawToken::Word(w)) if is_dup && w.text() == "-" => RedirectTarget::Close,
+        Some(RawToken::Word(w)) => match w.text().parse::<u8>() {
+            Ok(fd) if is_dup => RedirectTarget::Fd(fd),
+            _ => RedirectTarget::File(w.clone()),
+        },
+        _ => return Err(missing_target(tokens, i)),
+    })
+}
+
+/// 重定向缺少目标，行尾时和 bash 一样报告 newline，位置在前一个记号之后
+fn missing_target(tokens: &[Token], i: usize) -> ParseError {
+    match tokens.get(i) {
+        Some(token) => ParseError::UnexpectedToken {
+            token: token_text(&token.raw),
+            span: token.span,
+        },
+        None => ParseError::UnexpectedToken {
+            token: "newline".to_string(),
+            span: tokens[..i]
+                .last()
+                .map_or(Span::default(), |token| token.span.after()),
+        },
     }
 }
 
-use std::os::unix::io::FromRawFd;
 /// 命令执行上下文
 #[derive(Debug)]
 pub struct ExecutionContext<'a> {
-    pub stdin: Option<File>,
-    pub stdout: Option<File>,
-    pub stderr: Option<File>,
+    pub fds: FdTable,
     pub rl: &'a mut Editor<MyCompleter, FileHistory>,
+    pub state: &'a mut ShellState,
 }
 
 impl<'a> ExecutionContext<'a> {
-    pub fn new(rl: &'a mut Editor<MyCompleter, FileHistory>) -> Self {
+    pub fn new(rl: &'a mut Editor<MyCompleter, FileHistory>, state: &'a mut ShellState) -> Self {
         Self {
-            stdin: Some(unsafe { File::from_raw_fd(libc::dup(0)) }),
-            stdout: Some(unsafe { File::from_raw_fd(libc::dup(1)) }),
-            stderr: Some(unsafe { File::from_raw_fd(libc::dup(2)) }),
+            fds: FdTable::default(),
             rl,
+            state,
+        }
+    }
+
+    /// 为子命令复制一份 fd 表，避免重定向影响后续命令
+    pub fn duplicate(&mut self) -> anyhow::Result<ExecutionContext<'_>> {
+        Ok(ExecutionContext {
+            fds: self.fds.try_clone()?,
+            rl: self.rl,
+            state: self.state,
+        })
+    }
+}
+
+/// 执行任意类型的命令，返回最后一个命令的退出状态
+pub fn execute_command_type(
+    command_type: &CommandType,
+    context: &mut ExecutionContext,
+) -> anyhow::Result<CommandResult> {
+    let result = match command_type {
+        CommandType::Simple(command) => {
+            let result = excuete_single_command(command, &mut context.duplicate()?);
+            let result = report_error(result, context);
+            context.state.pipestatus = vec![result.exit_code];
+            check_errexit(result.exit_code, context);
+            result
+        }
+        CommandType::Pipeline(commands) => {
+            // 成功时 execute_pipeline 已记录每个命令的状态
+            let result = execute_pipeline(commands, &mut context.duplicate()?);
+            if result.is_err() {
+                context.state.pipestatus = vec![1];
+            }
+            let result = report_error(result, context);
+            check_errexit(result.exit_code, context);
+            result
+        }
+        CommandType::AndOr { first, rest } => {
+            // 除最后一个命令外，失败都不触发 set -e
+            let mut result = execute_condition(first, context)?;
+            for (i, (op, command_type)) in rest.iter().enumerate() {
+                // 短路求值：&& 仅在成功后执行，|| 仅在失败后执行
+                let run = match op {
+                    AndOrOp::And => result.exit_code == 0,
+                    AndOrOp::Or => result.exit_code != 0,
+                };
+                if !run {
+                    continue;
+                }
+                if context.state.flow.is_some() {
+                    break;
+                }
+                result = if i + 1 == rest.len() {
+                    execute_command_type(command_type, context)?
+                } else {
+                    execute_condition(command_type, context)?
+                };
+            }
+            result
+        }
+        CommandType::List(items) => {
+            let mut result = CommandResult::default();
+            for item in items {
+                result = execute_command_type(item, context)?;
+                if context.state.flow.is_some() {
+                    break;
+                }
+            }
+            result
+        }
+        CommandType::Background(command_type) => execute_background(command_type, context)?,
+        // 被反转的管道不触发 set -e
+        CommandType::Not(command_type) => {
+            let result = execute_condition(command_type, context)?;
+            CommandResult::new(i32::from(result.exit_code == 0))
+        }
+        CommandType::FunctionDef { name, body } => {
+            context.state.functions.insert(name.clone(), body.clone());
+            CommandResult::default()
+        }
+        CommandType::Compound {
+            command,
+            redirections,
+        } => {
+            let mut compound_context = context.duplicate()?;
+            let result = apply_redirections(redirections, &mut compound_context)
+                .and_then(|()| execute_compound(command, &mut compound_context));
+            let result = report_error(result, &mut compound_context);
+            check_errexit(result.exit_code, context);
+            result
+        }
+    };
+    // 记录退出状态供 $? 使用
+    context.state.last_status = result.exit_code;
+    Ok(result)
+}
+
+/// 在条件上下文中执行命令，其中的失败不触发 set -e
+pub fn execute_condition(
+    command_type: &CommandType,
+    context: &mut ExecutionContext,
+) -> anyhow::Result<CommandResult> {
+    context.state.condition_depth += 1;
+    let result = execute_command_type(command_type, context);
+    context.state.condition_depth -= 1;
+    result
+}
+
+/// 执行复合命令
+fn execute_compound(
+    command: &CompoundCommand,
+    context: &mut ExecutionContext,
+) -> anyhow::Result<CommandResult> {
+    match command {
+        CompoundCommand::If {
+            branches,
+            otherwise,
+        } => {
+            for (condition, body) in branches {
+                if execute_condition(condition, context)?.exit_code == 0 {
+                    return execute_command_type(body, context);
+                }
+            }
+            match otherwise {
+                Some(body) => execute_command_type(body, context),
+                // 没有分支执行时状态为 0
+                None => Ok(CommandResult::default()),
+            }
+        }
+        CompoundCommand::Loop {
+            condition,
+            body,
+            until,
+        } => in_loop(context, |context| {
+            let mut result = CommandResult::default();
+            loop {
+                let status = execute_condition(condition, context)?.exit_code;
+                if loop_should_stop(context.state) || (status == 0) == *until {
+                    break;
+                }
+                result = execute_command_type(body, context)?;
+                if loop_should_stop(context.state) {
+                    break;
+                }
+            }
+            Ok(result)
+        }),
+        CompoundCommand::For { name, words, body } => {
+            let values = match words {
+                Some(words) => expand_words(words, context)?,
+                None => context.state.positional.clone(),
+            };
+            in_loop(context, |context| {
+                let mut result = CommandResult::default();
+                for value in values {
+                    context.state.vars.set(name, value);
+                    result = execute_command_type(body, context)?;
+                    if loop_should_stop(context.state) {
+                        break;
+                    }
+                }
+                Ok(result)
+            })
+        }
+        CompoundCommand::Subshell(body) => {
+            let pgid = context.state.jobs.child_pgid();
+            let pid = fork_subshell(context, pgid, |context| execute_command_type(body, context))?;
+            context.state.jobs.register_child(pid);
+            let statuses = wait_results(
+                vec![CommandResult::forked(pid)],
+                command.to_string(),
+                context,
+            );
+            Ok(CommandResult::new(statuses[0]))
+        }
+        CompoundCommand::Group(body) => execute_command_type(body, context),
+        CompoundCommand::Case { word, items } => {
+            let text = expand_word(word, context)?;
+            let mut result = CommandResult::default();
+            let mut fall_through = false;
+            for item in items {
+                if !fall_through {
+                    let mut matched = false;
+                    for pattern in &item.patterns {
+                        if expand_pattern(pattern, context)?.matches(&text) {
+                            matched = true;
+                            break;
+                        }
+                    }
+                    if !matched {
+                        continue;
+                    }
+                }
+                if let Some(body) = &item.body {
+                    result = execute_command_type(body, context)?;
+                    if context.state.flow.is_some() {
+                        break;
+                    }
+                }
+                match item.terminator {
+                    CaseTerminator::Break => break,
+                    CaseTerminator::FallThrough => fall_through = true,
+                    CaseTerminator::Continue => fall_through = false,
+                }
+            }
+            Ok(result)
+        }
+    }
+}
+
+/// 在循环中执行，期间 break 和 continue 有效
+fn in_loop(
+    context: &mut ExecutionContext,
+    f: impl FnOnce(&mut ExecutionContext) -> anyhow::Result<CommandResult>,
+) -> anyhow::Result<CommandResult> {
+    context.state.loop_depth += 1;
+    let result = f(context);
+    context.state.loop_depth -= 1;
+    result
+}
+
+/// 处理 break、continue 和 return，返回是否结束当前循环
+fn loop_should_stop(state: &mut ShellState) -> bool {
+    match state.flow {
+        Some(Flow::Break(n)) => {
+            state.flow = (n > 1).then_some(Flow::Break(n - 1));
+            true
+        }
+        Some(Flow::Continue(n)) if n > 1 => {
+            state.flow = Some(Flow::Continue(n - 1));
+            true
+        }
+        Some(Flow::Continue(_)) => {
+            state.flow = None;
+            false
+        }
+        Some(Flow::Return | Flow::Interrupt) => true,
+        None => false,
+    }
+}
+
+/// set -e：命令失败且不在条件上下文中时退出 shell
+fn check_errexit(exit_code: i32, context: &mut ExecutionContext) {
+    if exit_code != 0 && context.state.options.errexit && context.state.condition_depth == 0 {
+        exit_shell(context, exit_code);
+    }
+}
+
+/// 退出 shell，只有交互式的主 shell 进程保存历史记录
+pub fn exit_shell(context: &mut ExecutionContext, code: i32) -> ! {
+    if context.state.interactive && std::process::id() == context.state.pid {
+        let _ = crate::history::write_history_file(context.rl);
+    }
+    std::process::exit(code & 0xff);
+}
+
+/// 展开或重定向失败只影响当前命令：输出错误并返回状态 1；
+/// 非交互式 shell 遇到未设置的变量等致命的展开错误时退出
+pub fn report_error(
+    result: anyhow::Result<CommandResult>,
+    context: &mut ExecutionContext,
+) -> CommandResult {
+    result.unwrap_or_else(|e| {
+        let result = print_error(&e, context);
+        if let Some(fatal) = e.downcast_ref::<FatalError>()
+            && !context.state.interactive
+        {
+            exit_shell(context, fatal.exit_code());
+        }
+        result
+    })
+}
+
+/// 输出错误并返回状态 1
+fn print_error(e: &anyhow::Error, context: &mut ExecutionContext) -> CommandResult {
+    let message = format!("{}{}\n", context.state.error_prefix(), e);
+    let _ = context.fds.write_all(2, message.as_bytes());
+    CommandResult::new(1)
+}
+
+/// 以 bash `type` 的格式输出函数定义，每条命令一行
+pub fn format_function(name: &str, body: &CommandType) -> String {
+    let mut text = format!("{} () \n", name);
+    let CommandType::Compound {
+        command,
+        redirections,
+    } = body
+    else {
+        return format!("{}{}\n", text, body);
+    };
+    let CompoundCommand::Group(list) = command.as_ref() else {
+        return format!("{}{}\n", text, body);
+    };
+    let items = match list {
+        CommandType::List(items) => items.as_slice(),
+        list => std::slice::from_ref(list),
+    };
+    text.push_str("{ \n");
+    for (i, item) in items.iter().enumerate() {
+        text.push_str(&format!("    {}", item));
+        if i + 1 < items.len() && !matches!(item, CommandType::Background(_)) {
+            text.push(';');
+        }
+        text.push('\n');
+    }
+    text.push('}');
+    for redirection in redirections {
+        text.push_str(&format!(" {}", redirection));
+    }
+    text.push('\n');
+    text
+}
+
+/// 输入是否为尚未结束的命令：引号、here-document 或复合命令未结束，或以 | && 等结尾
+pub fn is_incomplete(input: &str) -> bool {
+    match tokenize_line(input) {
+        Err(e) => e.downcast_ref::<LexError>().is_some(),
+        Ok(tokens) => {
+            matches!(parse_command(&tokens), Err(ParseError::UnexpectedEof))
+        }
+    }
+}
+
+/// 词法、语法分析并执行一段命令文本
+pub fn execute_source(
+    source: &str,
+    context: &mut ExecutionContext,
+) -> anyhow::Result<CommandResult> {
+    let raw_tokens = tokenize_line(source)?;
+    if raw_tokens.is_empty() {
+        return Ok(CommandResult::default());
+    }
+    let command_type = parse_command(&raw_tokens)?;
+    execute_command_type(&command_type, context)
+}
+
+/// 在 fork 出的子 shell 中执行后台作业，不等待其结束
+fn execute_background(
+    command_type: &CommandType,
+    context: &mut ExecutionContext,
+) -> anyhow::Result<CommandResult> {
+    let job_control = context.state.jobs.job_control();
+    let pid = fork_subshell(context, job_control.then_some(0), |context| {
+        // 没有作业控制时，后台命令不能读取终端
+        if !job_control && let Ok(null) = File::open("/dev/null") {
+            context.fds.set(0, null);
         }
+        execute_command_type(command_type, context)
+    })?;
+    if job_control {
+        unsafe { libc::setpgid(pid, pid) };
+    }
+    let id = context
+        .state
+        .jobs
+        .add(pid, &[pid], command_type.to_string());
+    context.state.last_bg_pid = Some(pid as u32);
+    if job_control {
+        eprintln!("[{}] {}", id, pid);
     }
+    Ok(CommandResult::default())
 }
-fn exit_code_by_child(child: Option<std::process::Child>) -> i32 {
-    child.map_or(0, |mut c| c.wait().ok().and_then(|e| e.code()).unwrap_or(1))
+
+/// fork 出子 shell 执行 f，子进程加入进程组 pgid 并以命令的状态退出；父进程返回子进程 pid
+fn fork_subshell(
+    context: &mut ExecutionContext,
+    pgid: Option<libc::pid_t>,
+    f: impl FnOnce(&mut ExecutionContext) -> anyhow::Result<CommandResult>,
+) -> anyhow::Result<libc::pid_t> {
+    match unsafe { libc::fork() } {
+        -1 => Err(std::io::Error::last_os_error().into()),
+        0 => {
+            let _ = crate::jobs::prepare_child(pgid);
+            context.state.jobs.enter_subshell();
+            let code = match f(context) {
+                Ok(result) => result.exit_code,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    1
+                }
+            };
+            std::process::exit(code);
+        }
+        pid => Ok(pid),
+    }
 }
+
+/// 等待前台命令启动的子进程，返回每个命令的退出状态
+fn wait_results(
+    results: Vec<CommandResult>,
+    command: String,
+    context: &mut ExecutionContext,
+) -> Vec<i32> {
+    let pids: Vec<libc::pid_t> = results.iter().filter_map(|result| result.pid).collect();
+    let statuses = context.state.jobs.wait_foreground(&pids, &command);
+    // 与 bash 一样，前台命令被 Ctrl-C 终止时不再执行命令行的其余部分
+    if context.state.jobs.job_control() && statuses.contains(&(128 + libc::SIGINT)) {
+        context.state.flow = Some(Flow::Interrupt);
+    }
+    let mut statuses = statuses.into_iter();
+    results
+        .into_iter()
+        .map(|result| match result.pid {
+            Some(_) => statuses.next().unwrap_or(1),
+            None => result.exit_code,
+        })
+        .collect()
+}
+
 pub fn excuete_single_command(
     command: &Command,
     context: &mut ExecutionContext,
 ) -> anyhow::Result<CommandResult> {
-    let mut res = execute_command(command, context)?;
-    Ok(CommandResult::new(exit_code_by_child(res.child.take())))
+    let result = execute_command(command, context)?;
+    let statuses = wait_results(vec![result], command.to_string(), context);
+    Ok(CommandResult::new(statuses[0]))
 }
 /// 执行命令
 pub fn execute_command(
     command: &Command,
     context: &mut ExecutionContext,
 ) -> anyhow::Result<CommandResult> {
+    // 展开参数和赋值
+    context.state.set_line(command.span);
+    context.state.subst_status = None;
+    let argv = expand_arguments(&command.argv, context)?;
+    let mut values = Vec::new();
+    for assignment in &command.assignments {
+        values.push(expand_word(&assignment.value, context)?);
+    }
+    if context.state.options.xtrace {
+        trace_command(command, &values, &argv, context);
+    }
+
     // 处理重定向
-    apply_redirections(command, context)?;
+    apply_redirections(&command.redirections, context)?;
 
-    // 执行命令
-    if command.argv.is_empty() {
-        return Ok(CommandResult::default());
+    // 没有命令名时，赋值作用于当前 shell，状态取最后一次命令替换的结果
+    if argv.is_empty() {
+        for (assignment, value) in command.assignments.iter().zip(values) {
+            context.state.vars.set(&assignment.name, value);
+        }
+        return Ok(CommandResult::new(
+            context.state.subst_status.unwrap_or_default(),
+        ));
     }
 
-    let command_name = &command.argv[0];
-    let args = command.argv[1..].to_vec();
+    // 命令前缀赋值只在该命令执行期间导出，结束后恢复原值
+    let mut saved = Vec::new();
+    for (assignment, value) in command.assignments.iter().zip(values) {
+        saved.push((
+            assignment.name.as_str(),
+            context.state.vars.take(&assignment.name),
+        ));
+        context
+            .state
+            .vars
+            .export(&assignment.name, Some(value), true);
+    }
+
+    let command_name = &argv[0];
+    let args = argv[1..].to_vec();
+    // 有停止作业时的 exit 警告只对紧接着的下一次 exit 有效
+    if command_name != "exit" {
+        context.state.exit_warned = false;
+    }
 
     // 使用简化的命令处理器
-    let handler = crate::CommandHandlerFactory::create_handler(command_name);
+    let handler = crate::CommandHandlerFactory::create_handler(command_name, context.state);
     let result = handler.execute(command_name, args, context);
+
+    for (name, var) in saved.into_iter().rev() {
+        context.state.vars.restore(name, var);
+    }
     Ok(result)
 }
 
-/// 应用重定向
-fn apply_redirections(command: &Command, context: &mut ExecutionContext) -> anyhow::Result<()> {
-    for redirection in &command.redirections {
-        match redirection.op {
-            RedirectOp::Out | RedirectOp::OutAppend => {
-                let fd = redirection.src_fd.unwrap_or(1); // 默认stdout
-                if fd == 1 {
-                    if let RedirectTarget::File(filename) = &redirection.target {
-                        let file = File::options()
-                            .write(true)
-                            .create(true)
-                            .append(redirection.op == RedirectOp::OutAppend)
-                            .open(filename)?;
-                        context.stdout = Some(file);
-                    }
-                } else if fd == 2
-                    && let RedirectTarget::File(filename) = &redirection.target
-                {
-                    let file = File::options()
-                        .write(true)
-                        .create(true)
-                        .append(redirection.op == RedirectOp::OutAppend)
-                        .open(filename)?;
-                    context.stderr = Some(file);
-                }
-            }
-            RedirectOp::In => {
-                let fd = redirection.src_fd.unwrap_or(0); // 默认stdin
-                if fd == 0
-                    && let RedirectTarget::File(filename) = &redirection.target
-                {
-                    let file = File::open(filename)?;
-                    context.stdin = Some(file);
-                }
-            }
-            RedirectOp::DupOut => {
-                // 处理文件描述符复制：2>&1
-                if let (Some(src_fd), RedirectTarget::Fd(target_fd)) =
-                    (redirection.src_fd, &redirection.target)
-                {
-                    // 这里需要更复杂的文件描述符复制逻辑
-                    // 简化实现：如果是stdout重定向到stderr或反之
-                    if src_fd == 2 && *target_fd == 1 {
-                        // stderr重定向到stdout
-                        context.stderr = context.stdout.take();
-                    } else if src_fd == 1 && *target_fd == 2 {
-                        // stdout重定向到stderr
-                        context.stdout = context.stderr.take();
-                    }
-                }
+/// 声明类内置命令，NAME=value 形式的参数按赋值展开
+const DECLARATION_BUILTINS: [&str; 3] = ["export", "declare", "local"];
+
+/// 展开命令的参数；声明类内置命令的 NAME=value 参数与赋值一样不分词，也不做路径名展开
+fn expand_arguments(argv: &[Word], context: &mut ExecutionContext) -> anyhow::Result<Vec<String>> {
+    let declaration = argv
+        .first()
+        .and_then(Word::unquoted_text)
+        .is_some_and(|name| DECLARATION_BUILTINS.contains(&name));
+    if !declaration {
+        return expand_words(argv, context);
+    }
+    let mut fields = Vec::new();
+    for word in argv {
+        if parse_assignment(word).is_some() {
+            fields.push(expand_word(word, context)?);
+        } else {
+            fields.extend(expand_words(std::slice::from_ref(word), context)?);
+        }
+    }
+    Ok(fields)
+}
+
+/// set -x：在标准错误输出展开后的命令，前缀为 PS4
+fn trace_command(
+    command: &Command,
+    values: &[String],
+    argv: &[String],
+    context: &mut ExecutionContext,
+) {
+    let mut words: Vec<String> = command
+        .assignments
+        .iter()
+        .zip(values)
+        .map(|(assignment, value)| format!("{}={}", assignment.name, shell_quote(value)))
+        .collect();
+    words.extend(argv.iter().map(|arg| shell_quote(arg)));
+    let prefix = context.state.vars.get("PS4").unwrap_or("+ ");
+    let line = format!("{}{}\n", prefix, words.join(" "));
+    let _ = context.fds.write_all(2, line.as_bytes());
+}
+
+/// 含有特殊字符的参数用单引号括起，使输出可以再次作为命令执行
+fn shell_quote(word: &str) -> String {
+    let plain = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%^".contains(c));
+    if plain {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
+
+/// 应用重定向，按从左到右的顺序修改命令的 fd 表
+fn apply_redirections(
+    redirections: &[Redirection],
+    context: &mut ExecutionContext,
+) -> anyhow::Result<()> {
+    for redirection in redirections {
+        context.state.set_line(redirection.span);
+        let fd = RawFd::from(redirection.src_fd.unwrap_or(match redirection.op {
+            RedirectOp::Out | RedirectOp::OutAppend | RedirectOp::Clobber | RedirectOp::DupOut => 1,
+            _ => 0,
+        }));
+        match (redirection.op, &redirection.target) {
+            (_, RedirectTarget::Heredoc(body)) => {
+                // 正文展开后写入内存文件
+                let body = expand_word(body, context)?;
+                context.fds.set(fd, heredoc_file(&body)?);
             }
-            RedirectOp::Heredoc => {
-                // 处理heredoc重定向
-                if let RedirectTarget::Heredoc(_content) = &redirection.target {
-                    // // 创建临时文件或管道来传递heredoc内容
-                    // // 简化实现：使用临时文件
-                    // let temp_file = tempfile::NamedTempFile::new()?;
-                    // std::fs::write(temp_file.path(), content)?;
-                    // let file = File::open(temp_file.path())?;
-                    // context.stdin = Some(file.as_raw_fd());
-                }
+            (RedirectOp::DupOut | RedirectOp::DupIn, RedirectTarget::Fd(source)) => {
+                context
+                    .fds
+                    .dup(fd, RawFd::from(*source))
+                    .map_err(|e| anyhow::anyhow!("{}: {}", source, strip_os_error(&e)))?;
+            }
+            (RedirectOp::DupOut | RedirectOp::DupIn, RedirectTarget::Close) => {
+                context.fds.close(fd);
+            }
+            // 没有 fd 编号的 >&file 等同于 &>file
+            (RedirectOp::DupOut, RedirectTarget::File(word)) if redirection.src_fd.is_none() => {
+                let file = open_redirect_file(RedirectOp::OutBoth, word, context)?;
+                context.fds.set(1, file);
+                context.fds.dup(2, 1)?;
+            }
+            (RedirectOp::DupOut | RedirectOp::DupIn, RedirectTarget::File(word)) => {
+                anyhow::bail!("{}: ambiguous redirect", word.text());
+            }
+            (op @ (RedirectOp::OutBoth | RedirectOp::AppendBoth), RedirectTarget::File(word)) => {
+                let file = open_redirect_file(op, word, context)?;
+                context.fds.set(1, file);
+                context.fds.dup(2, 1)?;
             }
-            _ => {
-                // 其他重定向操作符的实现在此省略，可根据需要添加
+            (op, RedirectTarget::File(word)) => {
+                let file = open_redirect_file(op, word, context)?;
+                context.fds.set(fd, file);
             }
+            _ => {}
         }
     }
     Ok(())
 }
 
+/// 按重定向操作符打开目标文件，noclobber 时 > 和 &> 拒绝覆盖已存在的普通文件
+fn open_redirect_file(
+    op: RedirectOp,
+    word: &Word,
+    context: &mut ExecutionContext,
+) -> anyhow::Result<File> {
+    let path = expand_word(word, context)?;
+    let mut options = File::options();
+    match op {
+        RedirectOp::Out | RedirectOp::OutBoth if context.state.options.noclobber => {
+            match std::fs::metadata(&path) {
+                Ok(metadata) if metadata.is_file() => {
+                    anyhow::bail!("{}: cannot overwrite existing file", path)
+                }
+                // 设备等非普通文件照常写入
+                Ok(_) => options.write(true),
+                Err(_) => options.write(true).create_new(true),
+            }
+        }
+        RedirectOp::Out | RedirectOp::OutBoth | RedirectOp::Clobber => {
+            options.write(true).create(true).truncate(true)
+        }
+        RedirectOp::OutAppend | RedirectOp::AppendBoth => options.append(true).create(true),
+        RedirectOp::ReadWrite => options.read(true).write(true).create(true),
+        _ => options.read(true),
+    };
+    options
+        .open(&path)
+        .map_err(|e| anyhow::anyhow!("{}: {}", path, strip_os_error(&e)))
+}
+
+/// 创建内容为 here-document 正文的匿名内存文件，读写位置置于开头
+fn heredoc_file(body: &str) -> anyhow::Result<File> {
+    let fd = unsafe { libc::memfd_create(c"heredoc".as_ptr(), libc::MFD_CLOEXEC) };
+    if fd == -1 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    let mut file = unsafe { File::from_raw_fd(fd) };
+    file.write_all(body.as_bytes())?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(file)
+}
+
+/// 在子 shell 中执行管道中的一个命令，函数、内置命令和赋值都不影响当前 shell，
+/// 各命令也能同时运行。next_reader 是下一个命令的管道读端，子 shell 必须关闭它，
+/// 否则写入端收不到 SIGPIPE
+fn execute_pipeline_member(
+    command: &CommandType,
+    next_reader: Option<&File>,
+    context: &mut ExecutionContext,
+) -> anyhow::Result<CommandResult> {
+    let next_reader = next_reader.map(File::as_raw_fd);
+    let pgid = context.state.jobs.child_pgid();
+    let pid = fork_subshell(context, pgid, |context| {
+        if let Some(fd) = next_reader {
+            unsafe { libc::close(fd) };
+        }
+        execute_command_type(command, context)
+    })?;
+    context.state.jobs.register_child(pid);
+    Ok(CommandResult::forked(pid))
+}
+
 /// 执行管道命令
 pub fn execute_pipeline(
-    commands: &[Command],
+    commands: &[CommandType],
     context: &mut ExecutionContext,
 ) -> anyhow::Result<CommandResult> {
     if commands.is_empty() {
@@ -260,48 +1612,47 @@ pub fn execute_pipeline(
     }
 
     let mut vec = vec![];
+    // 上一个命令的管道读端，作为下一个命令的标准输入
+    let mut stdin: Option<File> = None;
 
     for (i, command) in commands.iter().enumerate() {
         let is_last = i == commands.len() - 1;
-        // 设置管道
+        // 每个命令使用自己的 fd 表，执行后立即释放其中的管道端，
+        // 使等待期间 shell 不持有任何管道，读端退出后写端能收到 SIGPIPE
+        let mut command_context = ExecutionContext {
+            fds: context.fds.try_clone()?,
+            rl: context.rl,
+            state: context.state,
+        };
+        if let Some(stdin) = stdin.take() {
+            command_context.fds.set(0, stdin);
+        }
         if !is_last {
-            let mut fds = [0; 2];
-            unsafe { libc::pipe(fds.as_mut_ptr()) };
-
-            let reader = unsafe { File::from_raw_fd(fds[0]) };
-            let writer = unsafe { File::from_raw_fd(fds[1]) };
-
-            context.stdout = Some(writer);
-
-            // 执行当前命令
-            let mut command_context = ExecutionContext {
-                stdin: context.stdin.take(),
-                stdout: context.stdout.take(),
-                stderr: context.stderr.take(),
-                rl: context.rl,
-            };
-            let result = execute_command(command, &mut command_context)?;
-
-            context.stdin = Some(reader);
-            context.stdout = Some(unsafe { File::from_raw_fd(libc::dup(1)) });
-            context.stderr = Some(unsafe { File::from_raw_fd(libc::dup(2)) });
-
-            vec.push(result);
+            // 设置管道，执行当前命令
+            let (reader, writer) = crate::fd_table::pipe()?;
+            command_context.fds.set(1, writer);
+            let result = execute_pipeline_member(command, Some(&reader), &mut command_context);
+            vec.push(report_error(result, &mut command_context));
+            stdin = Some(reader);
         } else {
             // 最后一个命令
-            let result = execute_command(command, context)?;
-
-            vec.push(result);
+            let result = execute_pipeline_member(command, None, &mut command_context);
+            vec.push(report_error(result, &mut command_context));
         }
     }
-    let mut last_exit_code = 0;
-    for result in vec {
-        if let Some(mut child) = result.child {
-            let status = child.wait()?;
-            last_exit_code = status.code().unwrap_or(1);
-        } else {
-            last_exit_code = 0;
-        }
+    let text = commands
+        .iter()
+        .map(CommandType::to_string)
+        .collect::<Vec<_>>()
+        .join(" | ");
+    let statuses = wait_results(vec, text, context);
+    // pipefail：取最后一个失败命令的状态
+    let last_exit_code = if context.state.options.pipefail {
+        statuses.iter().rev().copied().find(|&code| code != 0)
+    } else {
+        statuses.last().copied()
     }
+    .unwrap_or_default();
+    context.state.pipestatus = statuses;
     Ok(CommandResult::new(last_exit_code))
 }
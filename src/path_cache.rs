@@ -0,0 +1,147 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex, MutexGuard},
+    time::SystemTime,
+};
+
+use is_executable::IsExecutable;
+
+use crate::utils::{find_all_executable_file_in_paths, find_executable_file_in_paths};
+
+/// 全局命令查找缓存，补全器与执行器共享
+static COMMAND_CACHE: LazyLock<Mutex<CommandCache>> =
+    LazyLock::new(|| Mutex::new(CommandCache::default()));
+
+pub fn command_cache() -> MutexGuard<'static, CommandCache> {
+    COMMAND_CACHE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// hash 表中的一项
+#[derive(Debug)]
+pub struct HashEntry {
+    pub path: PathBuf,
+    pub hits: usize, // 命中次数
+}
+
+/// 目录内容快照，目录的 mtime 变化时重新扫描
+#[derive(Debug)]
+struct DirListing {
+    mtime: SystemTime,
+    commands: Vec<String>,
+}
+
+/// 基于 PATH 的命令查找缓存，PATH 变化时失效
+#[derive(Debug, Default)]
+pub struct CommandCache {
+    path: String,
+    dirs: Vec<PathBuf>,
+    hashed: BTreeMap<String, HashEntry>,
+    listings: HashMap<PathBuf, DirListing>,
+}
+
+impl CommandCache {
+    /// 同步当前 PATH，变化时清空 hash 表
+    pub fn sync_path(&mut self, path: &str) {
+        if self.path != path {
+            self.path = path.to_string();
+            self.dirs = std::env::split_paths(path).collect();
+            self.hashed.clear();
+        }
+    }
+
+    /// 查找命令的可执行文件，含 / 的名称直接按路径处理
+    pub fn lookup(&mut self, name: &str) -> Option<PathBuf> {
+        if name.contains('/') {
+            return self.find(name);
+        }
+        if let Some(entry) = self.hashed.get_mut(name) {
+            // 已缓存的文件被删除或移动时重新查找
+            if entry.path.is_file() && entry.path.is_executable() {
+                entry.hits += 1;
+                return Some(entry.path.clone());
+            }
+            self.hashed.remove(name);
+        }
+        let path = find_executable_file_in_paths(name, &self.dirs)?;
+        self.hashed.insert(
+            name.to_string(),
+            HashEntry {
+                path: path.clone(),
+                hits: 1,
+            },
+        );
+        Some(path)
+    }
+
+    /// 查找命令但不修改 hash 表，用于 type 等只读场景
+    pub fn find(&self, name: &str) -> Option<PathBuf> {
+        if name.contains('/') {
+            let path = Path::new(name);
+            return (path.is_file() && path.is_executable()).then(|| path.to_path_buf());
+        }
+        find_executable_file_in_paths(name, &self.dirs)
+    }
+
+    /// 查找存在但不可执行的同名文件，用于区分 Permission denied 和 command not found
+    pub fn find_non_executable(&self, name: &str) -> Option<PathBuf> {
+        if name.contains('/') {
+            let path = Path::new(name);
+            return path.is_file().then(|| path.to_path_buf());
+        }
+        self.dirs
+            .iter()
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+    }
+
+    pub fn hashed(&self, name: &str) -> Option<&HashEntry> {
+        self.hashed.get(name)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&String, &HashEntry)> {
+        self.hashed.iter()
+    }
+
+    /// hash -p：手动指定命令路径
+    pub fn insert(&mut self, name: &str, path: PathBuf) {
+        self.hashed
+            .insert(name.to_string(), HashEntry { path, hits: 0 });
+    }
+
+    /// hash -d
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.hashed.remove(name).is_some()
+    }
+
+    /// hash -r
+    pub fn clear(&mut self) {
+        self.hashed.clear();
+    }
+
+    /// PATH 中所有可执行文件名，只重新扫描 mtime 变化过的目录
+    pub fn all_commands(&mut self) -> Vec<String> {
+        let mut commands = Vec::new();
+        for dir in &self.dirs {
+            let Ok(mtime) = dir.metadata().and_then(|meta| meta.modified()) else {
+                continue;
+            };
+            let listing = match self.listings.get(dir) {
+                Some(listing) if listing.mtime == mtime => listing,
+                _ => {
+                    let listing = DirListing {
+                        mtime,
+                        commands: find_all_executable_file_in_paths(std::slice::from_ref(dir))
+                            .iter()
+                            .filter_map(|path| path.file_name()?.to_str().map(str::to_string))
+                            .collect(),
+                    };
+                    self.listings.insert(dir.clone(), listing);
+                    &self.listings[dir]
+                }
+            };
+            commands.extend(listing.commands.iter().cloned());
+        }
+        commands
+    }
+}
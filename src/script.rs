@@ -0,0 +1,131 @@
+use std::{fs::File, io::Read, mem::ManuallyDrop, os::unix::io::FromRawFd};
+
+use crate::{
+    lexer::{LexError, tokenize_line},
+    parse::{ExecutionContext, ParseError, execute_command_type, parse_command},
+    state::Location,
+};
+
+/// 非交互模式下的命令来源
+pub enum LineSource {
+    Lines(std::vec::IntoIter<String>), // -c 字符串或脚本文件
+    Stdin,                             // 从标准输入逐行读取
+}
+
+impl LineSource {
+    pub fn from_text(text: &str) -> Self {
+        let lines: Vec<String> = text.lines().map(str::to_string).collect();
+        Self::Lines(lines.into_iter())
+    }
+
+    fn next_line(&mut self) -> Option<String> {
+        match self {
+            Self::Lines(lines) => lines.next(),
+            Self::Stdin => read_stdin_line(),
+        }
+    }
+}
+
+/// 逐字节读取标准输入的一行，不多读，使后续命令能读到剩余的输入
+fn read_stdin_line() -> Option<String> {
+    let mut stdin = ManuallyDrop::new(unsafe { File::from_raw_fd(0) });
+    let mut line = Vec::new();
+    let mut byte = [0u8];
+    loop {
+        match stdin.read(&mut byte) {
+            Ok(0) if line.is_empty() => return None,
+            Ok(0) => break,
+            Ok(_) if byte[0] == b'\n' => break,
+            Ok(_) => line.push(byte[0]),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(_) => return None,
+        }
+    }
+    Some(String::from_utf8_lossy(&line).into_owned())
+}
+
+/// 非交互地执行命令：每读到一条完整的命令就分析并执行，返回最后的退出状态。
+/// 错误信息以 name 和行号开头，语法错误时以状态 2 结束
+pub fn run_script(
+    mut source: LineSource,
+    name: &str,
+    noexec: bool,
+    context: &mut ExecutionContext,
+) -> i32 {
+    let mut input = String::new();
+    let mut line_number = 0;
+    let mut start_line = 1; // 当前命令的起始行号
+    loop {
+        let line = source.next_line();
+        let eof = line.is_none();
+        if let Some(line) = line {
+            line_number += 1;
+            if input.is_empty() {
+                start_line = line_number;
+            }
+            input.push_str(&line);
+            input.push('\n');
+        }
+        if input.trim().is_empty() {
+            input.clear();
+            if eof {
+                break;
+            }
+            continue;
+        }
+
+        let tokens = match tokenize_line(&input) {
+            // 命令还未结束，继续读取
+            Err(e) if e.downcast_ref::<LexError>().is_some() && !eof => continue,
+            // 文件以行接续结束时没有下一行可接，和 bash 一样去掉它
+            Err(e) if matches!(e.downcast_ref(), Some(LexError::TrailingBackslash)) => {
+                input.truncate(input.len() - "\\\n".len());
+                tokenize_line(&input)
+            }
+            result => result,
+        };
+        let tokens = match tokens {
+            Err(e) => return syntax_error(name, start_line, &input, e),
+            Ok(tokens) => tokens,
+        };
+        let command_type = match parse_command(&tokens) {
+            Err(ParseError::UnexpectedEof) if !eof => continue,
+            Ok(command_type) => command_type,
+            Err(e) => return syntax_error(name, start_line, &input, e.into()),
+        };
+        input.clear();
+        if !noexec {
+            let outer = context
+                .state
+                .location
+                .replace(Location::new(name, start_line));
+            if let Err(e) = execute_command_type(&command_type, context) {
+                eprintln!("{}: line {}: {}", name, start_line, e);
+            }
+            context.state.location = outer;
+        }
+        // return 等结束整个文件，由调用者处理
+        if eof || context.state.flow.is_some() {
+            break;
+        }
+    }
+    context.state.last_status
+}
+
+/// 报告语法错误，能定位时给出出错的行并标出位置
+fn syntax_error(name: &str, start_line: usize, input: &str, error: anyhow::Error) -> i32 {
+    let span = match error.downcast_ref::<ParseError>() {
+        Some(e) => e.span(),
+        // 未闭合的引号指向开始引号
+        None => error.downcast_ref::<LexError>().and_then(LexError::span),
+    };
+    match span {
+        Some(span) => {
+            let line = start_line + span.line - 1;
+            eprintln!("{}: line {}: {}", name, line, error);
+            eprintln!("{}", span.render(input));
+        }
+        None => eprintln!("{}: line {}: {}", name, start_line, error),
+    }
+    2
+}
@@ -0,0 +1,52 @@
+/// 源文本中的字节范围，line 为起始位置所在的行号（从 1 开始）
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+}
+
+impl Span {
+    /// 从本范围起始到 other 结束的范围
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end: other.end,
+            ..self
+        }
+    }
+
+    /// 本范围结束处的空范围，用于指出缺少的记号
+    pub fn after(self) -> Span {
+        Span {
+            start: self.end,
+            ..self
+        }
+    }
+
+    /// 输出范围所在的源代码行，并在下一行用 ^ 标出范围：
+    ///
+    /// ```text
+    /// echo a >
+    ///         ^
+    /// ```
+    pub fn render(&self, source: &str) -> String {
+        let start = self.start.min(source.len());
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[start..]
+            .find('\n')
+            .map_or(source.len(), |i| start + i);
+        // 制表符原样保留，使 ^ 与源代码对齐
+        let indent: String = source[line_start..start]
+            .chars()
+            .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+            .collect();
+        let end = self.end.clamp(start, line_end);
+        let width = source[start..end].chars().count().max(1);
+        format!(
+            "{}\n{}{}",
+            &source[line_start..line_end],
+            indent,
+            "^".repeat(width)
+        )
+    }
+}
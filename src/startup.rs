@@ -0,0 +1,65 @@
+use std::path::PathBuf;
+
+use crate::{
+    cli::CliArgs,
+    expand::expand_word,
+    lexer::{RawToken, tokenize_line},
+    parse::ExecutionContext,
+    script::{LineSource, run_script},
+};
+
+/// 系统级 profile
+const SYSTEM_PROFILE: &str = "/etc/profile";
+/// 用户 profile，使用第一个存在的文件
+const USER_PROFILES: [&str; 2] = [".shell_profile", ".profile"];
+/// 交互式 shell 的 rc 文件
+const RC_FILE: &str = ".shellrc";
+
+/// 读取启动文件：登录 shell 读取 profile，非登录的交互式 shell 读取 $ENV 或 ~/.shellrc
+pub fn load_startup_files(cli: &CliArgs, context: &mut ExecutionContext) {
+    let home = context.state.vars.get("HOME").map(PathBuf::from);
+    if cli.login {
+        if cli.noprofile {
+            return;
+        }
+        run_file(PathBuf::from(SYSTEM_PROFILE), context);
+        let profile = home.and_then(|home| {
+            USER_PROFILES
+                .iter()
+                .map(|name| home.join(name))
+                .find(|path| path.is_file())
+        });
+        if let Some(profile) = profile {
+            run_file(profile, context);
+        }
+    } else if cli.is_interactive() && !cli.norc {
+        match env_file(context) {
+            Some(path) => run_file(path, context),
+            None => {
+                if let Some(home) = home {
+                    run_file(home.join(RC_FILE), context);
+                }
+            }
+        }
+    }
+}
+
+/// $ENV 经参数展开后的文件名
+fn env_file(context: &mut ExecutionContext) -> Option<PathBuf> {
+    let value = context.state.vars.get("ENV")?.to_string();
+    let tokens = tokenize_line(&value).ok()?;
+    let Some(RawToken::Word(word)) = tokens.first().map(|token| &token.raw) else {
+        return None;
+    };
+    let path = expand_word(word, context).ok()?;
+    (!path.is_empty()).then(|| PathBuf::from(path))
+}
+
+/// 在当前 shell 中执行启动文件，不存在时忽略；出错时报告文件名和行号，继续启动
+fn run_file(path: PathBuf, context: &mut ExecutionContext) {
+    let Ok(text) = std::fs::read_to_string(&path) else {
+        return;
+    };
+    let name = path.display().to_string();
+    run_script(LineSource::from_text(&text), &name, false, context);
+}
@@ -0,0 +1,112 @@
+use std::{collections::HashMap, rc::Rc, sync::MutexGuard};
+
+use crate::{
+    jobs::JobTable,
+    options::ShellOptions,
+    parse::CommandType,
+    path_cache::{CommandCache, command_cache},
+    span::Span,
+    variables::Variables,
+};
+
+/// 改变执行流程的内置命令，在命令序列中逐层向外传递
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flow {
+    Return,          // return：结束当前函数或 source 的文件
+    Break(usize),    // break n：跳出 n 层循环
+    Continue(usize), // continue n：继续第 n 层循环的下一次迭代
+    Interrupt,       // 前台作业被 Ctrl-C 终止，放弃整个命令行
+}
+
+/// 正在执行的脚本位置
+#[derive(Debug, Clone)]
+pub struct Location {
+    pub name: String,      // 脚本文件名
+    pub start_line: usize, // 当前顶层命令的起始行号
+    pub line: usize,       // 正在执行的命令所在的行号
+}
+
+impl Location {
+    pub fn new(name: &str, start_line: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            start_line,
+            line: start_line,
+        }
+    }
+}
+
+/// 跨命令保留的 shell 状态
+#[derive(Debug)]
+pub struct ShellState {
+    pub vars: Variables,
+    pub last_status: i32,                            // $?
+    pub pipestatus: Vec<i32>,                        // 最近一个管道中每个命令的退出状态
+    pub subst_status: Option<i32>,                   // 当前命令中最后一次命令替换的状态
+    pub last_bg_pid: Option<u32>,                    // $!
+    pub shell_name: String,                          // $0
+    pub positional: Vec<String>,                     // $1 ... $n
+    pub pid: u32,                                    // $$，子 shell 中保持不变
+    pub options: ShellOptions,                       // shopt / set 选项
+    pub interactive: bool,                           // 交互模式（从终端读取命令）
+    pub location: Option<Location>,                  // 正在执行的脚本文件名和行号
+    pub jobs: JobTable,                              // 后台和停止的作业
+    pub exit_warned: bool,                           // 已提示有停止的作业，再次 exit 时直接退出
+    pub condition_depth: usize,                      // 大于 0 时处于条件上下文，set -e 不生效
+    pub source_depth: usize,                         // 正在 source 的文件层数
+    pub functions: HashMap<String, Rc<CommandType>>, // 已定义的函数及其函数体
+    pub function_depth: usize,                       // 正在执行的函数调用层数
+    pub loop_depth: usize,                           // 当前函数中嵌套的循环层数
+    pub flow: Option<Flow>,                          // 尚未处理的 return 等
+}
+
+impl ShellState {
+    pub fn new() -> Self {
+        Self {
+            vars: Variables::from_env(),
+            last_status: 0,
+            pipestatus: vec![0],
+            subst_status: None,
+            last_bg_pid: None,
+            shell_name: std::env::args().next().unwrap_or_default(),
+            positional: Vec::new(),
+            pid: std::process::id(),
+            options: ShellOptions::default(),
+            interactive: false,
+            location: None,
+            jobs: JobTable::default(),
+            exit_warned: false,
+            condition_depth: 0,
+            source_depth: 0,
+            functions: HashMap::new(),
+            function_depth: 0,
+            loop_depth: 0,
+            flow: None,
+        }
+    }
+
+    /// 执行脚本时错误信息的前缀 "name: line N: "
+    pub fn error_prefix(&self) -> String {
+        match &self.location {
+            Some(location) => format!("{}: line {}: ", location.name, location.line),
+            None => String::new(),
+        }
+    }
+
+    /// 按命令或重定向在顶层命令中的位置更新错误信息的行号；
+    /// 函数体来自定义处的源文本，其中保持调用处的行号
+    pub fn set_line(&mut self, span: Span) {
+        if self.function_depth == 0
+            && let Some(location) = &mut self.location
+        {
+            location.line = location.start_line + span.line.saturating_sub(1);
+        }
+    }
+
+    /// 获取与当前 PATH 同步的命令查找缓存
+    pub fn command_cache(&self) -> MutexGuard<'static, CommandCache> {
+        let mut cache = command_cache();
+        cache.sync_path(self.vars.get("PATH").unwrap_or(""));
+        cache
+    }
+}
@@ -40,3 +40,31 @@ pub fn find_all_executable_file_in_paths(paths: &[PathBuf]) -> Vec<PathBuf> {
         })
         .collect()
 }
+
+/// 等待子进程结束，返回 shell 风格的退出状态（被信号终止时为 128+信号值）
+pub fn wait_for_pid(pid: libc::pid_t) -> std::io::Result<i32> {
+    let mut status = 0;
+    loop {
+        if unsafe { libc::waitpid(pid, &mut status, 0) } != -1 {
+            break;
+        }
+        let err = std::io::Error::last_os_error();
+        if err.kind() != std::io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+    if libc::WIFSIGNALED(status) {
+        Ok(128 + libc::WTERMSIG(status))
+    } else {
+        Ok(libc::WEXITSTATUS(status))
+    }
+}
+
+/// 去掉 io::Error 显示文本末尾的 " (os error N)"，与 bash 的报错格式一致
+pub fn strip_os_error(err: &std::io::Error) -> String {
+    let text = err.to_string();
+    match text.find(" (os error") {
+        Some(pos) => text[..pos].to_string(),
+        None => text,
+    }
+}
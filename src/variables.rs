@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+/// 单个 shell 变量
+#[derive(Debug, Clone)]
+pub struct Variable {
+    pub value: Option<String>, // None 表示仅声明（如 export NAME）而未赋值
+    pub exported: bool,        // 是否导出到子进程环境
+}
+
+/// shell 变量表
+#[derive(Debug, Clone, Default)]
+pub struct Variables {
+    vars: HashMap<String, Variable>,
+    scopes: Vec<Vec<(String, Option<Variable>)>>, // 每层函数调用中被 local 覆盖的变量及其原值
+}
+
+impl Variables {
+    /// 从进程环境初始化，环境变量默认处于导出状态
+    pub fn from_env() -> Self {
+        let vars = std::env::vars()
+            .map(|(name, value)| {
+                (
+                    name,
+                    Variable {
+                        value: Some(value),
+                        exported: true,
+                    },
+                )
+            })
+            .collect();
+        Self {
+            vars,
+            scopes: Vec::new(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.vars.get(name).and_then(|var| var.value.as_deref())
+    }
+
+    pub fn get_variable(&self, name: &str) -> Option<&Variable> {
+        self.vars.get(name)
+    }
+
+    /// 设置变量值，保留已有的导出属性
+    pub fn set(&mut self, name: &str, value: String) {
+        self.vars
+            .entry(name.to_string())
+            .or_insert(Variable {
+                value: None,
+                exported: false,
+            })
+            .value = Some(value);
+    }
+
+    /// 设置导出属性，value 为 None 时保留原值
+    pub fn export(&mut self, name: &str, value: Option<String>, exported: bool) {
+        let var = self.vars.entry(name.to_string()).or_insert(Variable {
+            value: None,
+            exported,
+        });
+        var.exported = exported;
+        if value.is_some() {
+            var.value = value;
+        }
+    }
+
+    pub fn unset(&mut self, name: &str) {
+        self.vars.remove(name);
+    }
+
+    /// 取出变量，与 restore 配合实现命令前缀赋值的临时作用域
+    pub fn take(&mut self, name: &str) -> Option<Variable> {
+        self.vars.remove(name)
+    }
+
+    pub fn restore(&mut self, name: &str, var: Option<Variable>) {
+        match var {
+            Some(var) => self.vars.insert(name.to_string(), var),
+            None => self.vars.remove(name),
+        };
+    }
+
+    /// 进入函数调用，之后的 local 变量在 pop_scope 时恢复原值
+    pub fn push_scope(&mut self) {
+        self.scopes.push(Vec::new());
+    }
+
+    pub fn pop_scope(&mut self) {
+        if let Some(saved) = self.scopes.pop() {
+            for (name, var) in saved.into_iter().rev() {
+                self.restore(&name, var);
+            }
+        }
+    }
+
+    /// 在当前函数调用中声明局部变量，不在函数中时返回 false。
+    /// 被调用的函数也能看到调用者的局部变量（动态作用域）
+    pub fn make_local(&mut self, name: &str, value: Option<String>) -> bool {
+        let Some(scope) = self.scopes.last_mut() else {
+            return false;
+        };
+        if scope.iter().any(|(local, _)| local == name) {
+            // 已是局部变量：不带值的再次声明保留当前值
+            if value.is_some() {
+                self.set(name, value.unwrap_or_default());
+            }
+            return true;
+        }
+        scope.push((name.to_string(), self.vars.get(name).cloned()));
+        self.vars.insert(
+            name.to_string(),
+            Variable {
+                value,
+                exported: false,
+            },
+        );
+        true
+    }
+
+    /// 传递给子进程的环境变量
+    pub fn exported(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.vars.iter().filter_map(|(name, var)| match &var.value {
+            Some(value) if var.exported => Some((name.as_str(), value.as_str())),
+            _ => None,
+        })
+    }
+
+    /// 按名称排序的全部变量
+    pub fn sorted(&self) -> Vec<(&str, &Variable)> {
+        let mut vars: Vec<_> = self
+            .vars
+            .iter()
+            .map(|(name, var)| (name.as_str(), var))
+            .collect();
+        vars.sort_by_key(|(name, _)| *name);
+        vars
+    }
+}
+
+/// 是否为合法的变量名：[A-Za-z_][A-Za-z0-9_]*
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// 以 bash `declare -p` 的格式输出变量
+pub fn declare_line(name: &str, var: &Variable) -> String {
+    let flag = if var.exported { "-x" } else { "--" };
+    match &var.value {
+        Some(value) => {
+            let mut quoted = String::new();
+            for ch in value.chars() {
+                if matches!(ch, '"' | '\\' | '$' | '`') {
+                    quoted.push('\\');
+                }
+                quoted.push(ch);
+            }
+            format!("declare {} {}=\"{}\"\n", flag, name, quoted)
+        }
+        None => format!("declare {} {}\n", flag, name),
+    }
+}